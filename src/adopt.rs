@@ -0,0 +1,325 @@
+use crate::{
+    atomic,
+    config::{self, Config},
+    symlink::{self, SymLink},
+};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug)]
+pub enum Error {
+    /// Path to adopt is not inside the home directory
+    NotInHome(PathBuf),
+
+    /// Path to adopt is already a symbolic link
+    AlreadyLink(PathBuf),
+
+    /// Path in the dotfiles directory already exists
+    Exists(PathBuf),
+
+    /// Group can not receive a new entry with a text edit
+    InvalidGroup(String),
+
+    /// Config file is not valid after adding the entry
+    Config(config::Error),
+
+    /// Error creating the symbolic link
+    SymLink(symlink::Error),
+
+    /// io error moving the file
+    Io(io::Error),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::NotInHome(path) => write!(f, "{:?} is not inside the home directory", path),
+            Error::AlreadyLink(path) => write!(f, "{:?} is already a symbolic link", path),
+            Error::Exists(path) => write!(f, "{:?} already exists", path),
+            Error::InvalidGroup(group) => {
+                write!(f, "Unable to add a link entry to group {}", group)
+            }
+            Error::Config(err) => err.fmt(f),
+            Error::SymLink(err) => err.fmt(f),
+            Error::Io(err) => err.fmt(f),
+        }
+    }
+}
+
+impl From<config::Error> for Error {
+    fn from(val: config::Error) -> Self {
+        Error::Config(val)
+    }
+}
+
+impl From<symlink::Error> for Error {
+    fn from(val: symlink::Error) -> Self {
+        Error::SymLink(val)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(val: io::Error) -> Self {
+        Error::Io(val)
+    }
+}
+
+/// Move a file or directory of home into the dotfiles directory, the directory
+/// of config file, under the same path relative to home. The original path is
+/// replaced by a link to the moved file and a `link` entry is appended to group.
+pub fn adopt(config_file: &Path, path: &Path, group: &str, home: &Path) -> Result<SymLink, Error> {
+    let config_file = fs::canonicalize(config_file)?;
+    let repo = config_file.parent().unwrap_or_else(|| Path::new("/"));
+    let home = fs::canonicalize(home)?;
+    let path = absolute(path)?;
+
+    if path.symlink_metadata()?.file_type().is_symlink() {
+        return Err(Error::AlreadyLink(path));
+    }
+    let relative = match path.strip_prefix(&home) {
+        Ok(relative) if !relative.as_os_str().is_empty() && !path.starts_with(repo) => relative,
+        _ => return Err(Error::NotInHome(path)),
+    };
+    let target = repo.join(relative);
+    if target.symlink_metadata().is_ok() {
+        return Err(Error::Exists(target));
+    }
+
+    let link = SymLink::new(&tilde(&path, &home), &tilde(&target, &home), false, true);
+    let entry = format!("link: {} -> {}", link.src, link.dst);
+    let content = add_entry(&fs::read_to_string(&config_file)?, group, &entry)?;
+    Config::new(&content)?;
+
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    move_path(&path, &target)?;
+    let absolute = SymLink::new(
+        &path.to_string_lossy(),
+        &target.to_string_lossy(),
+        false,
+        true,
+    );
+    if let Err(err) = symlink::symlink(&absolute) {
+        move_path(&target, &path)?;
+        return Err(Error::SymLink(err));
+    }
+
+    atomic::write(&config_file, content.as_bytes())?;
+    Ok(link)
+}
+
+/// Append an entry to the values of group, editing the content of config as
+/// text to keep its formatting and comments. The group is created at the end
+/// of config if it does not exists.
+pub fn add_entry(content: &str, group: &str, entry: &str) -> Result<String, Error> {
+    let lines: Vec<&str> = content.lines().collect();
+    let header = format!("{}:", group);
+    let start = match lines.iter().position(|line| line.starts_with(&header)) {
+        // Values in flow style, like `group: []`, can not be extended.
+        Some(start) if !is_blank(&lines[start][header.len()..]) => {
+            return Err(Error::InvalidGroup(group.to_string()));
+        }
+        Some(start) => start,
+        None => {
+            let mut content = content.to_string();
+            if !content.is_empty() && !content.ends_with('\n') {
+                content.push('\n');
+            }
+            return Ok(format!("{}\n{}\n  - {}\n", content, header, entry));
+        }
+    };
+
+    // Lines of group end at the next line that is not indented.
+    let end = (start + 1..lines.len())
+        .find(|&i| !is_blank(lines[i]) && indent(lines[i]) == 0)
+        .unwrap_or(lines.len());
+
+    // In the mapping form, the values are the entries after the `values` key.
+    let mut region = start + 1..end;
+    if let Some(values) = region
+        .clone()
+        .find(|&i| lines[i].trim_start().starts_with("values:"))
+    {
+        let key = indent(lines[values]);
+        let values_end = (values + 1..end)
+            .find(|&i| {
+                !is_blank(lines[i])
+                    && (indent(lines[i]) < key
+                        || (indent(lines[i]) == key && !lines[i].trim_start().starts_with('-')))
+            })
+            .unwrap_or(end);
+        region = values + 1..values_end;
+    } else if region
+        .clone()
+        .find(|&i| !is_blank(lines[i]))
+        .is_some_and(|i| !lines[i].trim_start().starts_with('-'))
+    {
+        // Group is a mapping without values.
+        return Err(Error::InvalidGroup(group.to_string()));
+    }
+
+    let first = region
+        .clone()
+        .find(|&i| lines[i].trim_start().starts_with("- "))
+        .ok_or_else(|| Error::InvalidGroup(group.to_string()))?;
+    let last = region.rev().find(|&i| !is_blank(lines[i])).unwrap_or(first);
+
+    let item = format!("{}- {}", " ".repeat(indent(lines[first])), entry);
+    let mut edited: Vec<&str> = lines[..=last].to_vec();
+    edited.push(&item);
+    edited.extend_from_slice(&lines[last + 1..]);
+    Ok(edited.join("\n") + "\n")
+}
+
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+fn is_blank(line: &str) -> bool {
+    let line = line.trim_start();
+    line.is_empty() || line.starts_with('#')
+}
+
+fn absolute(path: &Path) -> Result<PathBuf, Error> {
+    let path = PathBuf::from(shellexpand::tilde(&path.to_string_lossy()).as_ref());
+    if path.is_absolute() {
+        return Ok(path);
+    }
+    Ok(std::env::current_dir()?.join(path))
+}
+
+// Write a path inside home as `~/path`.
+fn tilde(path: &Path, home: &Path) -> String {
+    match path.strip_prefix(home) {
+        Ok(relative) => format!("~/{}", relative.display()),
+        Err(_) => path.display().to_string(),
+    }
+}
+
+// Move a file or directory, copying it when it is on other file system.
+fn move_path(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Err(err) if err.raw_os_error() == Some(libc::EXDEV) => {
+            copy(from, to)?;
+            if from.is_dir() {
+                fs::remove_dir_all(from)
+            } else {
+                fs::remove_file(from)
+            }
+        }
+        result => result,
+    }
+}
+
+fn copy(from: &Path, to: &Path) -> io::Result<()> {
+    if !from.is_dir() {
+        return fs::copy(from, to).map(|_| ());
+    }
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        copy(&entry.path(), &to.join(entry.file_name()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_add_entry() {
+        let content = "# my dotfiles
+base:
+  - package: git # version control
+
+dev:
+  order: 1
+  after:
+    - base
+  values:
+    - package: tmux
+      # terminal multiplexer
+
+    - package: neovim
+  requires: [base]
+";
+        assert_eq!(
+            "# my dotfiles
+base:
+  - package: git # version control
+  - link: a -> b
+
+dev:
+  order: 1
+  after:
+    - base
+  values:
+    - package: tmux
+      # terminal multiplexer
+
+    - package: neovim
+  requires: [base]
+",
+            add_entry(content, "base", "link: a -> b").unwrap()
+        );
+        assert_eq!(
+            "# my dotfiles
+base:
+  - package: git # version control
+
+dev:
+  order: 1
+  after:
+    - base
+  values:
+    - package: tmux
+      # terminal multiplexer
+
+    - package: neovim
+    - link: a -> b
+  requires: [base]
+",
+            add_entry(content, "dev", "link: a -> b").unwrap()
+        );
+        assert_eq!(
+            format!("{}\ngui:\n  - link: a -> b\n", content),
+            add_entry(content, "gui", "link: a -> b").unwrap()
+        );
+        assert!(add_entry("gui: []\n", "gui", "link: a -> b").is_err());
+        assert!(add_entry("gui:\n  after:\n  - base\n", "gui", "link: a -> b").is_err());
+    }
+
+    #[test]
+    fn test_adopt() {
+        let home = tempdir().unwrap();
+        let repo = home.path().join(".dotfiles");
+        let config_file = repo.join("dotfiles.yml");
+        let path = home.path().join(".config/foo/config");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::create_dir_all(&repo).unwrap();
+        fs::write(&path, "foo").unwrap();
+        fs::write(&config_file, "dev:\n  - package: foo\n").unwrap();
+
+        let link = adopt(&config_file, &path, "dev", home.path()).unwrap();
+        assert_eq!("~/.config/foo/config", link.dst);
+        assert_eq!("~/.dotfiles/.config/foo/config", link.src);
+
+        let target = repo.join(".config/foo/config");
+        assert_eq!("foo", fs::read_to_string(&target).unwrap());
+        assert_eq!(target, fs::read_link(&path).unwrap());
+        assert_eq!(
+            "dev:\n  - package: foo\n  - link: ~/.dotfiles/.config/foo/config -> ~/.config/foo/config\n",
+            fs::read_to_string(&config_file).unwrap()
+        );
+
+        assert!(matches!(
+            adopt(&config_file, &path, "dev", home.path()),
+            Err(Error::AlreadyLink(_))
+        ));
+    }
+}
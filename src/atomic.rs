@@ -0,0 +1,39 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Replace the content of file atomically: the content is written and synced
+/// to a temporary file next to it, that is then renamed over the file. A run
+/// interrupted at any point leaves either the old or the new content.
+pub fn write(path: &Path, content: &[u8]) -> io::Result<()> {
+    let tmp = tmp_path(path);
+    let mut file = File::create(&tmp)?;
+    file.write_all(content)?;
+    file.sync_all()?;
+    fs::rename(&tmp, path)
+}
+
+// Path of the temporary file, like `state.json.tmp` for `state.json`.
+fn tmp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_write() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("dotfiles.yml");
+        assert_eq!(dir.path().join("dotfiles.yml.tmp"), tmp_path(&path));
+
+        write(&path, b"base:\n").unwrap();
+        write(&path, b"dev:\n").unwrap();
+        assert_eq!("dev:\n", fs::read_to_string(&path).unwrap());
+        assert!(!tmp_path(&path).exists());
+    }
+}
@@ -1,10 +1,64 @@
-use clap::{crate_authors, crate_description, crate_name, crate_version, App, Arg};
+use crate::{init::Selection, list::Format};
+use clap::{
+    crate_authors, crate_description, crate_name, crate_version, App, AppSettings, Arg, ArgMatches,
+    ErrorKind, SubCommand,
+};
 use std::path::PathBuf;
 
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    /// Install and configure the values of config file, also used when no
+    /// subcommand is given.
+    Apply,
+
+    /// Only validate the config file.
+    Check,
+
+    /// Show which values are out of sync with the machine.
+    Status,
+
+    /// Show the diff of link destinations that conflict with a file.
+    Diff,
+
+    /// Move a file of home into the dotfiles directory and link it.
+    Adopt { path: PathBuf, group: String },
+
+    /// Create the config file, seeded with the selected packages and dotfiles.
+    Init { selection: Selection, list: bool },
+
+    /// Add the installed packages missing from config to group.
+    ExportPackages { group: String },
+
+    /// Show the packages installed but not in config, and the opposite.
+    DiffPackages,
+
+    /// Show the groups of config and their entries.
+    List {
+        group: Option<String>,
+        entries: bool,
+        format: Format,
+    },
+}
+
 pub struct Options {
+    pub command: Command,
     pub config_file: PathBuf,
-    pub only_group: Option<String>,
-    pub except_group: Option<String>,
+    /// Groups or values to configure, see `Ipa::setup_selection`.
+    pub only: Vec<String>,
+
+    /// Groups or values never configured, even when required by others.
+    pub except: Vec<String>,
+
+    /// Tags of values to configure.
+    pub tags: Vec<String>,
+
+    /// Tags of values never configured.
+    pub skip_tags: Vec<String>,
+    pub no_requires: bool,
+    pub become_command: String,
+    pub allow_root: bool,
+    pub keep_going: bool,
+    pub diff: bool,
     pub verbose: usize,
     pub quiet: bool,
 }
@@ -12,9 +66,17 @@ pub struct Options {
 impl Default for Options {
     fn default() -> Self {
         Options {
+            command: Command::Apply,
             config_file: PathBuf::new(),
-            only_group: None,
-            except_group: None,
+            only: vec![],
+            except: vec![],
+            tags: vec![],
+            skip_tags: vec![],
+            no_requires: false,
+            become_command: String::from("sudo"),
+            allow_root: false,
+            keep_going: false,
+            diff: false,
             verbose: 0,
             quiet: false,
         }
@@ -23,64 +85,469 @@ impl Default for Options {
 
 impl Options {
     pub fn new() -> Self {
-        let matches = App::new(crate_name!())
-            .version(crate_version!())
-            .author(crate_authors!("\n"))
-            .about(crate_description!())
-            .arg(
-                Arg::with_name("file")
-                    .long("file")
-                    .short("f")
-                    .required(false)
-                    .default_value("dotfiles.yml")
-                    .takes_value(true),
-            )
-            .arg(
-                Arg::with_name("only-group")
-                    .value_name("group")
-                    .long("only")
-                    .required(false)
-                    .takes_value(true),
-            )
-            .arg(
-                Arg::with_name("except-group")
-                    .value_name("group")
-                    .long("except")
-                    .required(false)
-                    .takes_value(true),
-            )
-            .arg(
-                Arg::with_name("verbose")
-                    .short("v")
-                    .multiple(true)
-                    .help("Increase message verbosity. -v -vv"),
-            )
-            .arg(
-                Arg::with_name("quiet")
-                    .short("q")
-                    .help("Silence all output"),
-            )
-            .get_matches();
+        Options::from_matches(&app().get_matches()).unwrap_or_else(|error| error.exit())
+    }
 
+    /// Parse the options from command line arguments, including the program name.
+    pub fn parse<I, T>(args: I) -> Result<Self, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<std::ffi::OsString> + Clone,
+    {
+        Options::from_matches(&app().get_matches_from_safe(args)?)
+    }
+
+    fn from_matches(matches: &ArgMatches) -> Result<Self, clap::Error> {
         let mut options = Options::default();
 
-        if let Some(config_file) = matches.value_of("file") {
-            options.config_file = PathBuf::from(config_file);
+        // Options of apply given before another subcommand would be ignored.
+        if !matches!(matches.subcommand(), ("apply", _) | ("", _)) {
+            if let Some((_, flag)) = APPLY_ARGS
+                .iter()
+                .find(|(name, _)| matches.occurrences_of(name) > 0)
+            {
+                return Err(clap::Error::with_description(
+                    &format!(
+                        "The argument '{}' can only be used with the apply subcommand",
+                        flag
+                    ),
+                    ErrorKind::ArgumentConflict,
+                ));
+            }
         }
 
-        if let Some(only_group) = matches.value_of("only-group") {
-            options.only_group = Some(only_group.to_owned());
+        match matches.subcommand() {
+            ("apply", Some(apply)) => options.apply(&[matches, apply]),
+            ("check", _) => options.command = Command::Check,
+            ("status", _) => options.command = Command::Status,
+            ("diff", _) => options.command = Command::Diff,
+            ("adopt", Some(adopt)) => {
+                options.command = Command::Adopt {
+                    path: PathBuf::from(adopt.value_of("path").unwrap_or_default()),
+                    group: adopt.value_of("group").unwrap_or_default().to_owned(),
+                }
+            }
+            ("init", Some(init)) => {
+                let values = |name| {
+                    init.values_of(name)
+                        .map(|values| values.map(String::from).collect())
+                        .unwrap_or_default()
+                };
+                options.command = Command::Init {
+                    selection: Selection {
+                        packages: init.is_present("packages"),
+                        dotfiles: init.is_present("dotfiles"),
+                        include: values("include"),
+                        exclude: values("exclude"),
+                    },
+                    list: init.is_present("list"),
+                }
+            }
+            ("packages", Some(packages)) => match packages.subcommand() {
+                ("export", Some(export)) => {
+                    options.command = Command::ExportPackages {
+                        group: export.value_of("group").unwrap_or_default().to_owned(),
+                    }
+                }
+                _ => options.command = Command::DiffPackages,
+            },
+            ("list", Some(list)) => {
+                options.command = Command::List {
+                    group: list.value_of("group").map(String::from),
+                    entries: list.is_present("entries"),
+                    format: list
+                        .value_of("format")
+                        .and_then(|format| format.parse().ok())
+                        .unwrap_or(Format::Text),
+                }
+            }
+            // Without subcommand ipa applies the config, as it always did.
+            _ => options.apply(&[matches]),
         }
 
-        if let Some(except_group) = matches.value_of("except-group") {
-            options.except_group = Some(except_group.to_owned());
+        // Global options are only propagated down, to the last subcommand.
+        let mut global = matches;
+        while let (_, Some(subcommand)) = global.subcommand() {
+            global = subcommand;
+        }
+
+        if let Some(config_file) = global.value_of("file") {
+            options.config_file = PathBuf::from(config_file);
         }
 
         // Enable Info level by default
-        options.verbose = matches.occurrences_of("verbose") as usize + 2;
+        options.verbose = global.occurrences_of("verbose") as usize + 2;
+
+        options.quiet = global.is_present("quiet");
+
+        Ok(options)
+    }
+
+    /// Read the options of apply, merging the ones given before the `apply`
+    /// subcommand with the ones given after it.
+    fn apply(&mut self, matches: &[&ArgMatches]) {
+        self.command = Command::Apply;
+
+        // Targets can be repeated and separated by commas, like `--only dev,gui`.
+        let targets = |name| -> Vec<String> {
+            matches
+                .iter()
+                .flat_map(|matches| matches.values_of(name))
+                .flatten()
+                .flat_map(|value| value.split(','))
+                .map(str::trim)
+                .filter(|target| !target.is_empty())
+                .map(String::from)
+                .collect()
+        };
+
+        self.only = targets("only-group");
+
+        self.except = targets("except-group");
+
+        self.tags = targets("tags");
+
+        self.skip_tags = targets("skip-tags");
+
+        let flag = |name| matches.iter().any(|matches| matches.is_present(name));
+
+        self.no_requires = flag("no-requires");
+
+        // The last given wins, falling back to the default value.
+        if let Some(become_command) = matches
+            .iter()
+            .rev()
+            .find(|matches| matches.occurrences_of("become-command") > 0)
+            .or_else(|| matches.last())
+            .and_then(|matches| matches.value_of("become-command"))
+        {
+            self.become_command = become_command.to_owned();
+        }
+
+        self.allow_root = flag("allow-root");
+
+        self.keep_going = flag("keep-going");
+
+        self.diff = flag("diff");
+    }
+}
+
+fn app() -> App<'static, 'static> {
+    App::new(crate_name!())
+        .version(crate_version!())
+        .author(crate_authors!("\n"))
+        .about(crate_description!())
+        .arg(
+            Arg::with_name("file")
+                .long("file")
+                .short("f")
+                .required(false)
+                .default_value("dotfiles.yml")
+                .global(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("verbose")
+                .short("v")
+                .multiple(true)
+                .global(true)
+                .help("Increase message verbosity. -v -vv"),
+        )
+        .arg(
+            Arg::with_name("quiet")
+                .short("q")
+                .global(true)
+                .help("Silence all output"),
+        )
+        .args(&apply_args())
+        .subcommand(
+            SubCommand::with_name("apply")
+                .about("Install and configure the values of config file, the default")
+                .args(&apply_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("check")
+                .about("Validate the config file, reporting all problems found"),
+        )
+        .subcommand(
+            SubCommand::with_name("status")
+                .about("Show which values are out of sync, exiting with error if any"),
+        )
+        .subcommand(
+            SubCommand::with_name("diff")
+                .about("Show the diff of link destinations that conflict with a file"),
+        )
+        .subcommand(
+            SubCommand::with_name("adopt")
+                .about("Move a file into the dotfiles directory, replacing it with a link")
+                .arg(Arg::with_name("path").required(true))
+                .arg(
+                    Arg::with_name("group")
+                        .long("group")
+                        .short("g")
+                        .required(true)
+                        .help("Group that receives the link entry")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("init")
+                .about("Create the config file and its directory, seeded with packages and dotfiles found")
+                .arg(
+                    Arg::with_name("packages")
+                        .long("packages")
+                        .help("Include all packages installed explicitly"),
+                )
+                .arg(
+                    Arg::with_name("dotfiles")
+                        .long("dotfiles")
+                        .help("Adopt all dotfiles found in home"),
+                )
+                .arg(
+                    Arg::with_name("include")
+                        .value_name("name")
+                        .long("include")
+                        .multiple(true)
+                        .number_of_values(1)
+                        .help("Include a package or a dotfile relative to home, like .config/nvim")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("exclude")
+                        .value_name("name")
+                        .long("exclude")
+                        .multiple(true)
+                        .number_of_values(1)
+                        .help("Never include a package or a dotfile relative to home")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("list")
+                        .long("list")
+                        .help("Only list the packages and dotfiles found"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("packages")
+                .about("Compare the packages of config with the installed ones")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("export")
+                        .about("Add the installed packages missing from config to a group")
+                        .arg(
+                            Arg::with_name("group")
+                                .long("group")
+                                .short("g")
+                                .required(true)
+                                .help("Group that receives the package entries")
+                                .takes_value(true),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("diff")
+                        .about("List packages installed but not in config, and the opposite"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("list")
+                .about("Show the groups of config with their number of entries")
+                .arg(
+                    Arg::with_name("group")
+                        .long("group")
+                        .short("g")
+                        .help("Show only the entries of group")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("entries")
+                        .long("verbose")
+                        .help("Show the packages, links and shell commands of each entry"),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .possible_values(&["text", "json"])
+                        .default_value("text")
+                        .takes_value(true),
+                ),
+        )
+}
+
+// Names and flags of the arguments of apply.
+const APPLY_ARGS: [(&str, &str); 9] = [
+    ("only-group", "--only"),
+    ("except-group", "--except"),
+    ("tags", "--tags"),
+    ("skip-tags", "--skip-tags"),
+    ("no-requires", "--no-requires"),
+    ("become-command", "--become-command"),
+    ("allow-root", "--allow-root"),
+    ("keep-going", "--keep-going"),
+    ("diff", "--diff"),
+];
+
+// Arguments of apply, accepted by the `apply` subcommand and without subcommand.
+fn apply_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("only-group")
+            .value_name("group")
+            .long("only")
+            .required(false)
+            .multiple(true)
+            .number_of_values(1)
+            .help("Configure only groups, or single values with group/value. Accepts globs like dev-*")
+            .takes_value(true),
+        Arg::with_name("except-group")
+            .value_name("group")
+            .long("except")
+            .required(false)
+            .multiple(true)
+            .number_of_values(1)
+            .help("Do not configure groups or values, even when required by others")
+            .takes_value(true),
+        Arg::with_name("tags")
+            .value_name("tag")
+            .long("tags")
+            .multiple(true)
+            .number_of_values(1)
+            .help("Configure only values with any of the tags, in them or in their group")
+            .takes_value(true),
+        Arg::with_name("skip-tags")
+            .value_name("tag")
+            .long("skip-tags")
+            .multiple(true)
+            .number_of_values(1)
+            .help("Do not configure values with any of the tags, even when required by others")
+            .takes_value(true),
+        Arg::with_name("no-requires")
+            .long("no-requires")
+            .help("Do not configure the groups required by the --only group"),
+        Arg::with_name("become-command")
+            .value_name("command")
+            .long("become-command")
+            .default_value("sudo")
+            .help("Command used to install packages and run shell entries as other user")
+            .takes_value(true),
+        Arg::with_name("allow-root")
+            .long("allow-root")
+            .help("Allow running ipa as root"),
+        Arg::with_name("keep-going")
+            .long("keep-going")
+            .short("k")
+            .help("Continue when a value fails, reporting all failures at the end"),
+        Arg::with_name("diff")
+            .long("diff")
+            .help("Dry run: only show the diff of conflicting link destinations, changing nothing"),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_without_subcommand() {
+        let options = Options::parse(["ipa", "-f", "config.yml", "--only", "dev", "-k"]).unwrap();
+        assert_eq!(Command::Apply, options.command);
+        assert_eq!(PathBuf::from("config.yml"), options.config_file);
+        assert_eq!(vec!["dev"], options.only);
+        assert!(options.keep_going);
+
+        let options =
+            Options::parse(["ipa", "-f", "config.yml", "apply", "--only", "dev", "-k"]).unwrap();
+        assert_eq!(Command::Apply, options.command);
+        assert_eq!(vec!["dev"], options.only);
+        assert!(options.keep_going);
+    }
+
+    #[test]
+    fn test_multiple_targets() {
+        let options = Options::parse([
+            "ipa", "--only", "dev,gui", "--only", "base/git", "--except", "dev-*",
+        ])
+        .unwrap();
+        assert_eq!(vec!["dev", "gui", "base/git"], options.only);
+        assert_eq!(vec!["dev-*"], options.except);
+
+        // Ids of values with many packages are separated by spaces, not commas.
+        let options = Options::parse(["ipa", "--only", "dev/fzf ripgrep,gui"]).unwrap();
+        assert_eq!(vec!["dev/fzf ripgrep", "gui"], options.only);
+
+        let options =
+            Options::parse(["ipa", "apply", "--tags", "rust,go", "--skip-tags", "gui"]).unwrap();
+        assert_eq!(vec!["rust", "go"], options.tags);
+        assert_eq!(vec!["gui"], options.skip_tags);
+    }
+
+    #[test]
+    fn test_global_options() {
+        let options = Options::parse(["ipa", "status", "-f", "config.yml", "-v"]).unwrap();
+        assert_eq!(Command::Status, options.command);
+        assert_eq!(PathBuf::from("config.yml"), options.config_file);
+        assert_eq!(3, options.verbose);
+
+        let options = Options::parse([
+            "ipa",
+            "-q",
+            "-f",
+            "config.yml",
+            "packages",
+            "export",
+            "-g",
+            "base",
+        ])
+        .unwrap();
+        assert_eq!(
+            Command::ExportPackages {
+                group: String::from("base")
+            },
+            options.command
+        );
+        assert_eq!(PathBuf::from("config.yml"), options.config_file);
+        assert!(options.quiet);
+
+        assert!(Options::parse(["ipa", "status", "--only", "dev"]).is_err());
+    }
+
+    #[test]
+    fn test_apply_options_before_subcommand() {
+        let error = Options::parse(["ipa", "--only", "dev", "status"])
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::ArgumentConflict, error.kind);
+        assert!(error.message.contains("'--only'"));
+        assert!(Options::parse(["ipa", "-k", "list"]).is_err());
+        assert!(Options::parse(["ipa", "--become-command", "doas", "check"]).is_err());
+
+        let options = Options::parse([
+            "ipa",
+            "--only",
+            "dev",
+            "-k",
+            "--become-command",
+            "doas",
+            "apply",
+            "--only",
+            "gui",
+        ])
+        .unwrap();
+        assert_eq!(Command::Apply, options.command);
+        assert_eq!(vec!["dev", "gui"], options.only);
+        assert!(options.keep_going);
+        assert_eq!("doas", options.become_command);
 
-        options.quiet = matches.is_present("quiet");
+        let options = Options::parse([
+            "ipa",
+            "--become-command",
+            "doas",
+            "apply",
+            "--become-command",
+            "run0",
+        ])
+        .unwrap();
+        assert_eq!("run0", options.become_command);
 
-        options
+        let options = Options::parse(["ipa", "apply"]).unwrap();
+        assert_eq!("sudo", options.become_command);
     }
 }
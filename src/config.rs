@@ -1,6 +1,15 @@
-use crate::{pacman::Package, shell::Shell, symlink::SymLink};
-use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, fs, io, path::Path};
+use crate::{pacman::Package, shell::Shell, symlink::SymLink, validate};
+use log::warn;
+use serde::{
+    de::{self, value::MapAccessDeserializer, value::SeqAccessDeserializer},
+    Deserialize, Deserializer, Serialize,
+};
+use std::{
+    fmt, fs, io,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
 #[derive(Debug)]
 pub enum Error {
@@ -12,6 +21,9 @@ pub enum Error {
 
     /// Not valid yaml or missing parameters.
     Yaml(serde_yaml::Error),
+
+    /// Problems found validating the config file.
+    Invalid(Vec<validate::Problem>),
 }
 
 impl std::fmt::Display for Error {
@@ -20,6 +32,13 @@ impl std::fmt::Display for Error {
             Error::NotFound => write!(f, "Unable to locate config file"),
             Error::Io(e) => write!(f, "{}", e),
             Error::Yaml(e) => write!(f, "Problem with config: {}", e),
+            Error::Invalid(problems) => {
+                write!(f, "Found {} problem(s) with config:", problems.len())?;
+                for problem in problems.iter() {
+                    write!(f, "\n  {}", problem)?;
+                }
+                Ok(())
+            }
         }
     }
 }
@@ -41,36 +60,523 @@ impl From<serde_yaml::Error> for Error {
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
 pub struct Values {
-    pub package: Option<Package>,
+    /// Name used to refer to this value, see `Values::id`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    #[serde(
+        rename = "package",
+        default,
+        skip_serializing_if = "Vec::is_empty",
+        deserialize_with = "one_or_many"
+    )]
+    pub packages: Vec<Package>,
+
+    #[serde(default, deserialize_with = "shorthand")]
     pub link: Option<SymLink>,
+
+    #[serde(default, deserialize_with = "shorthand")]
     pub shell: Option<Shell>,
+
+    /// Groups, or values in the form `group/value`, that must be
+    /// configured before this value.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub requires: Vec<String>,
+
+    /// Handlers executed at the end of the run when this value changes.
+    #[serde(
+        default,
+        skip_serializing_if = "Vec::is_empty",
+        deserialize_with = "one_or_many"
+    )]
+    pub notify: Vec<String>,
+
+    /// Tags used to select values across groups, added to the tags of group.
+    #[serde(
+        default,
+        skip_serializing_if = "Vec::is_empty",
+        deserialize_with = "one_or_many"
+    )]
+    pub tags: Vec<String>,
 }
 
 impl Values {
     pub fn new(package: Option<Package>, link: Option<SymLink>, shell: Option<Shell>) -> Self {
         Values {
-            package,
+            name: None,
+            packages: package.into_iter().collect(),
             link,
             shell,
+            requires: vec![],
+            notify: vec![],
+            tags: vec![],
+        }
+    }
+
+    /// Stable id of value inside its group: the name of value if it has one,
+    /// otherwise the package names separated by spaces, the link destination
+    /// or the shell command. Package names are not separated by commas, since
+    /// they separate the targets of `--only` and `--except`.
+    pub fn id(&self) -> String {
+        if let Some(ref name) = self.name {
+            return name.clone();
+        }
+        if !self.packages.is_empty() {
+            let names: Vec<&str> = self.packages.iter().map(|p| p.name.as_str()).collect();
+            return names.join(" ");
+        }
+        if let Some(ref link) = self.link {
+            return link.dst.clone();
+        }
+        if let Some(ref shell) = self.shell {
+            return shell.command_line();
+        }
+        String::new()
+    }
+
+    /// Check if the value, or its group, has any of the tags.
+    pub fn tagged(&self, group: &Group, tags: &[String]) -> bool {
+        self.tags
+            .iter()
+            .chain(group.tags.iter())
+            .any(|tag| tags.contains(tag))
+    }
+}
+
+/// Split a reference to a group or to a value of group, like `dev` or
+/// `dev/neovim`, into the group name and the value id.
+pub fn split_target(target: &str) -> (&str, Option<&str>) {
+    let mut parts = target.splitn(2, '/');
+    let group = parts.next().unwrap_or_default();
+    (group, parts.next())
+}
+
+/// Shell commands executed before and after configuring a group, or the whole run.
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Hooks {
+    #[serde(
+        default,
+        skip_serializing_if = "Vec::is_empty",
+        deserialize_with = "one_or_many"
+    )]
+    pub before: Vec<Shell>,
+
+    #[serde(
+        default,
+        skip_serializing_if = "Vec::is_empty",
+        deserialize_with = "one_or_many"
+    )]
+    pub after: Vec<Shell>,
+
+    /// Only execute the `after` hooks when some value was changed.
+    #[serde(default)]
+    pub only_changed: bool,
+}
+
+/// Group of values that can be configured individually.
+///
+/// A group can be written as a list of values or as a mapping with
+/// the values and options about when the group is configured.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct Group {
+    #[serde(skip)]
+    pub name: String,
+
+    /// Groups with lower order are configured first.
+    pub order: i64,
+
+    /// Groups that must be configured before this one, when selected.
+    pub after: Vec<String>,
+
+    /// Groups that must be configured before this one.
+    pub requires: Vec<String>,
+
+    pub hooks: Hooks,
+
+    /// Tags of all values of group.
+    pub tags: Vec<String>,
+
+    pub values: Vec<Values>,
+}
+
+impl Group {
+    pub fn new(name: &str, values: Vec<Values>) -> Self {
+        Group {
+            name: name.to_string(),
+            order: 0,
+            after: vec![],
+            requires: vec![],
+            hooks: Hooks::default(),
+            tags: vec![],
+            values,
+        }
+    }
+}
+
+/// Mapping form of a group, see `Group`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct GroupOptions {
+    #[serde(default)]
+    order: i64,
+
+    #[serde(default)]
+    after: Vec<String>,
+
+    #[serde(default)]
+    requires: Vec<String>,
+
+    #[serde(default)]
+    hooks: Hooks,
+
+    #[serde(default, deserialize_with = "one_or_many")]
+    tags: Vec<String>,
+
+    #[serde(default)]
+    values: Vec<Values>,
+}
+
+impl<'de> Deserialize<'de> for Group {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct GroupVisitor;
+
+        impl<'de> de::Visitor<'de> for GroupVisitor {
+            type Value = Group;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a list of values or a mapping")
+            }
+
+            fn visit_seq<S>(self, seq: S) -> Result<Self::Value, S::Error>
+            where
+                S: de::SeqAccess<'de>,
+            {
+                let values = Deserialize::deserialize(SeqAccessDeserializer::new(seq))?;
+                Ok(Group::new("", values))
+            }
+
+            fn visit_map<M>(self, map: M) -> Result<Self::Value, M::Error>
+            where
+                M: de::MapAccess<'de>,
+            {
+                let options = GroupOptions::deserialize(MapAccessDeserializer::new(map))?;
+                Ok(Group {
+                    name: String::new(),
+                    order: options.order,
+                    after: options.after,
+                    requires: options.requires,
+                    hooks: options.hooks,
+                    tags: options.tags,
+                    values: options.values,
+                })
+            }
         }
+
+        deserializer.deserialize_any(GroupVisitor)
     }
 }
 
+/// Value that can be written either as a shorthand string, parsed with `FromStr`,
+/// or as the full mapping form of `T`.
+struct Shorthand<T>(T);
+
+impl<'de, T> Deserialize<'de> for Shorthand<T>
+where
+    T: Deserialize<'de> + FromStr,
+    T::Err: fmt::Display,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct ShorthandVisitor<T>(PhantomData<T>);
+
+        impl<'de, T> de::Visitor<'de> for ShorthandVisitor<T>
+        where
+            T: Deserialize<'de> + FromStr,
+            T::Err: fmt::Display,
+        {
+            type Value = Shorthand<T>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a string or a mapping")
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                T::from_str(value).map(Shorthand).map_err(E::custom)
+            }
+
+            fn visit_map<M>(self, map: M) -> Result<Self::Value, M::Error>
+            where
+                M: de::MapAccess<'de>,
+            {
+                T::deserialize(MapAccessDeserializer::new(map)).map(Shorthand)
+            }
+        }
+
+        deserializer.deserialize_any(ShorthandVisitor(PhantomData))
+    }
+}
+
+fn shorthand<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + FromStr,
+    T::Err: fmt::Display,
+{
+    Ok(Option::<Shorthand<T>>::deserialize(deserializer)?.map(|value| value.0))
+}
+
+/// Accept a single shorthand/mapping value or a sequence of them.
+fn one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + FromStr,
+    T::Err: fmt::Display,
+{
+    struct OneOrManyVisitor<T>(PhantomData<T>);
+
+    impl<'de, T> de::Visitor<'de> for OneOrManyVisitor<T>
+    where
+        T: Deserialize<'de> + FromStr,
+        T::Err: fmt::Display,
+    {
+        type Value = Vec<T>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a string, a mapping or a list of them")
+        }
+
+        fn visit_unit<E>(self) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(vec![])
+        }
+
+        fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            T::from_str(value).map(|v| vec![v]).map_err(E::custom)
+        }
+
+        fn visit_map<M>(self, map: M) -> Result<Self::Value, M::Error>
+        where
+            M: de::MapAccess<'de>,
+        {
+            T::deserialize(MapAccessDeserializer::new(map)).map(|v| vec![v])
+        }
+
+        fn visit_seq<S>(self, seq: S) -> Result<Self::Value, S::Error>
+        where
+            S: de::SeqAccess<'de>,
+        {
+            let values: Vec<Shorthand<T>> =
+                Deserialize::deserialize(SeqAccessDeserializer::new(seq))?;
+            Ok(values.into_iter().map(|value| value.0).collect())
+        }
+    }
+
+    deserializer.deserialize_any(OneOrManyVisitor(PhantomData))
+}
+
+/// Top level key of config file reserved for the hooks of the whole run.
+pub const HOOKS: &str = "hooks";
+
+/// Top level key of config file reserved for the handlers.
+pub const HANDLERS: &str = "handlers";
+
+/// Shell command executed once at the end of the run, when notified by a
+/// value that changed.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct Handler {
+    pub name: String,
+    pub shell: Shell,
+}
+
+// Deserialize the handlers keeping the order that they are declared.
+fn handlers<'de, M>(map: &mut M) -> Result<Vec<Handler>, M::Error>
+where
+    M: de::MapAccess<'de>,
+{
+    struct HandlersVisitor;
+
+    impl<'de> de::Visitor<'de> for HandlersVisitor {
+        type Value = Vec<Handler>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a mapping of handler names to shell commands")
+        }
+
+        fn visit_map<M>(self, mut map: M) -> Result<Self::Value, M::Error>
+        where
+            M: de::MapAccess<'de>,
+        {
+            let mut handlers: Vec<Handler> = vec![];
+            while let Some((name, shell)) = map.next_entry::<String, Shorthand<Shell>>()? {
+                handlers.push(Handler {
+                    name,
+                    shell: shell.0,
+                });
+            }
+            Ok(handlers)
+        }
+    }
+
+    struct Handlers(Vec<Handler>);
+
+    impl<'de> Deserialize<'de> for Handlers {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_map(HandlersVisitor).map(Handlers)
+        }
+    }
+
+    Ok(map.next_value::<Handlers>()?.0)
+}
+
 #[derive(Debug, PartialEq)]
 pub struct Config {
-    pub values: HashMap<String, Vec<Values>>,
+    /// Groups in the same order that they are declared in config file.
+    pub groups: Vec<Group>,
+
+    /// Hooks of the whole run, declared with the reserved `hooks` key.
+    pub hooks: Hooks,
+
+    /// Handlers in the same order that they are declared in config file.
+    pub handlers: Vec<Handler>,
+
+    /// Directory of config file, when loaded from a file.
+    pub dir: Option<PathBuf>,
+}
+
+impl<'de> Deserialize<'de> for Config {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct ConfigVisitor;
+
+        impl<'de> de::Visitor<'de> for ConfigVisitor {
+            type Value = Config;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a mapping of groups")
+            }
+
+            fn visit_map<M>(self, mut map: M) -> Result<Self::Value, M::Error>
+            where
+                M: de::MapAccess<'de>,
+            {
+                let mut groups: Vec<Group> = vec![];
+                let mut hooks = Hooks::default();
+                let mut handlers = vec![];
+                while let Some(name) = map.next_key::<String>()? {
+                    if name == HOOKS {
+                        hooks = map.next_value()?;
+                        continue;
+                    }
+                    if name == HANDLERS {
+                        handlers = self::handlers(&mut map)?;
+                        continue;
+                    }
+                    if groups.iter().any(|g| g.name == name) {
+                        return Err(de::Error::custom(format!("duplicated group {}", name)));
+                    }
+                    let mut group: Group = map.next_value()?;
+                    group.name = name;
+                    groups.push(group);
+                }
+                Ok(Config {
+                    groups,
+                    hooks,
+                    handlers,
+                    dir: None,
+                })
+            }
+        }
+
+        deserializer.deserialize_map(ConfigVisitor)
+    }
 }
 
 impl Config {
     pub fn new(content: &str) -> Result<Self, Error> {
-        let values: HashMap<String, Vec<Values>> = serde_yaml::from_str(&content)?;
-        Ok(Config { values })
+        Ok(serde_yaml::from_str(content)?)
+    }
+
+    pub fn group(&self, name: &str) -> Option<&Group> {
+        self.groups.iter().find(|group| group.name == name)
+    }
+
+    pub fn handler(&self, name: &str) -> Option<&Handler> {
+        self.handlers.iter().find(|handler| handler.name == name)
     }
 
+    /// Load and validate the config file, logging the warnings found. Relative
+    /// sources of links are resolved from the directory of config file, while
+    /// the ones of configs created with `new` stay relative to the working
+    /// directory.
     pub fn load(config_file: &Path) -> Result<Self, Error> {
+        Config::read(config_file, false)
+    }
+
+    /// Load the config file like `load`, but failing on warnings too, like
+    /// sources of links that does not exists.
+    pub fn check(config_file: &Path) -> Result<Self, Error> {
+        Config::read(config_file, true)
+    }
+
+    fn read(config_file: &Path, strict: bool) -> Result<Self, Error> {
         let content = fs::read_to_string(config_file)?;
-        Config::new(&content)
+        let (warnings, problems): (Vec<_>, Vec<_>) = validate::validate(&content, config_file)
+            .into_iter()
+            .partition(|problem| problem.warning && !strict);
+        if !problems.is_empty() {
+            return Err(Error::Invalid(problems));
+        }
+        for warning in warnings.iter() {
+            warn!("{}", warning);
+        }
+        let mut config = Config::new(&content)?;
+        config.dir = fs::canonicalize(config_file)?
+            .parent()
+            .map(Path::to_path_buf);
+        if let Some(dir) = config.dir.clone() {
+            config.resolve_sources(&dir);
+        }
+        Ok(config)
+    }
+
+    // Make the relative sources of links relative to the directory of config
+    // file, instead of the directory where ipa is executed.
+    fn resolve_sources(&mut self, dir: &Path) {
+        let links = self
+            .groups
+            .iter_mut()
+            .flat_map(|group| group.values.iter_mut())
+            .filter_map(|value| value.link.as_mut());
+        for link in links {
+            let relative = match shellexpand::full(&link.src) {
+                Ok(src) => Path::new(src.as_ref()).is_relative(),
+                Err(_) => false,
+            };
+            if relative {
+                link.src = dir.join(&link.src).to_string_lossy().to_string();
+            }
+        }
     }
 }
 
@@ -99,28 +605,241 @@ dev:
       relink: true
 ";
         let config = Config::new(&content).unwrap();
-        let mut values = HashMap::new();
-        values.insert(
-            String::from("gui"),
+        let groups = vec![
+            Group::new(
+                "gui",
+                vec![
+                    Values::new(
+                        Some(Package::new("neovim")),
+                        Some(SymLink::new("foo/bar", "foo/baz", false, true)),
+                        Some(Shell::new("echo foo")),
+                    ),
+                    Values::new(Some(Package::new("alacritty")), None, None),
+                ],
+            ),
+            Group::new(
+                "dev",
+                vec![Values::new(
+                    None,
+                    Some(SymLink::new("bar/foo", "baz/foo", true, true)),
+                    None,
+                )],
+            ),
+        ];
+
+        let expected_config = Config {
+            groups,
+            hooks: Hooks::default(),
+            handlers: vec![],
+            dir: None,
+        };
+        assert_eq!(expected_config, config);
+    }
+
+    #[test]
+    fn test_serialize_shorthands() {
+        let content = r#"
+dev:
+  - package: tmux
+  - package: [fzf, ripgrep]
+    link: "~/.dotfiles/git/gitconfig -> ~/.gitconfig"
+    shell: "nvim +PlugInstall +qall"
+"#;
+        let config = Config::new(content).unwrap();
+        let groups = vec![Group::new(
+            "dev",
             vec![
-                Values::new(
-                    Some(Package::new("neovim")),
-                    Some(SymLink::new("foo/bar", "foo/baz", false, true)),
-                    Some(Shell::new("echo foo")),
-                ),
-                Values::new(Some(Package::new("alacritty")), None, None),
+                Values::new(Some(Package::new("tmux")), None, None),
+                Values {
+                    name: None,
+                    packages: vec![Package::new("fzf"), Package::new("ripgrep")],
+                    link: Some(SymLink::new(
+                        "~/.gitconfig",
+                        "~/.dotfiles/git/gitconfig",
+                        false,
+                        true,
+                    )),
+                    shell: Some(Shell::new("nvim +PlugInstall +qall")),
+                    requires: vec![],
+                    notify: vec![],
+                    tags: vec![],
+                },
             ],
+        )];
+
+        assert_eq!(
+            Config {
+                groups,
+                hooks: Hooks::default(),
+                handlers: vec![],
+                dir: None,
+            },
+            config
         );
-        values.insert(
-            String::from("dev"),
-            vec![Values::new(
-                None,
-                Some(SymLink::new("bar/foo", "baz/foo", true, true)),
-                None,
-            )],
+    }
+
+    #[test]
+    fn test_group_options() {
+        let content = "
+gui:
+  order: 10
+  after: [base]
+  values:
+    - package: i3
+
+base:
+  - package: git
+";
+        let config = Config::new(content).unwrap();
+        let names: Vec<&str> = config.groups.iter().map(|g| g.name.as_str()).collect();
+        assert_eq!(vec!["gui", "base"], names);
+
+        let gui = config.group("gui").unwrap();
+        assert_eq!(10, gui.order);
+        assert_eq!(vec![String::from("base")], gui.after);
+        assert_eq!(1, gui.values.len());
+    }
+
+    #[test]
+    fn test_hooks() {
+        let content = "
+hooks:
+  after: notify-send done
+
+gui:
+  hooks:
+    before: [i3-msg reload]
+    after:
+      - command: fc-cache -f
+        quiet: true
+    only_changed: true
+  values:
+    - package: i3
+";
+        let config = Config::new(content).unwrap();
+        assert_eq!(1, config.groups.len());
+        assert_eq!(vec![Shell::new("notify-send done")], config.hooks.after);
+
+        let hooks = &config.group("gui").unwrap().hooks;
+        assert_eq!(vec![Shell::new("i3-msg reload")], hooks.before);
+        assert_eq!("fc-cache -f", hooks.after[0].command);
+        assert!(hooks.after[0].quiet);
+        assert!(hooks.only_changed);
+    }
+
+    #[test]
+    fn test_handlers() {
+        let content = "
+handlers:
+  restart dunst: systemctl --user restart dunst
+  reload i3:
+    command: i3-msg reload
+
+gui:
+  - link: ~/.dotfiles/dunstrc -> ~/.config/dunst/dunstrc
+    notify: restart dunst
+  - package: i3
+    notify: [reload i3, restart dunst]
+";
+        let config = Config::new(content).unwrap();
+        let names: Vec<&str> = config.handlers.iter().map(|h| h.name.as_str()).collect();
+        assert_eq!(vec!["restart dunst", "reload i3"], names);
+        assert_eq!(
+            Shell::new("i3-msg reload"),
+            config.handler("reload i3").unwrap().shell
         );
 
-        let expected_config = Config { values };
-        assert_eq!(expected_config, config);
+        let values = &config.group("gui").unwrap().values;
+        assert_eq!(vec![String::from("restart dunst")], values[0].notify);
+        assert_eq!(2, values[1].notify.len());
+    }
+
+    #[test]
+    fn test_value_ids() {
+        let content = "
+dev:
+  - name: editor
+    package: neovim
+  - package: [fzf, ripgrep]
+  - link: ~/.dotfiles/git/gitconfig -> ~/.gitconfig
+  - shell: nvim +PlugInstall +qall
+";
+        let config = Config::new(content).unwrap();
+        let ids: Vec<String> = config.groups[0].values.iter().map(Values::id).collect();
+        assert_eq!(
+            vec![
+                "editor",
+                "fzf ripgrep",
+                "~/.gitconfig",
+                "nvim +PlugInstall +qall"
+            ],
+            ids
+        );
+        assert_eq!(
+            ("dev", Some("~/.config/nvim")),
+            split_target("dev/~/.config/nvim")
+        );
+        assert_eq!(("dev", None), split_target("dev"));
+    }
+
+    #[test]
+    fn test_load_relative_src() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_file = dir.path().join("dotfiles.yml");
+        fs::write(dir.path().join("vimrc"), "").unwrap();
+        fs::write(
+            &config_file,
+            "dev:\n  - link: vimrc -> ~/.vimrc\n  - link: /etc/hosts -> ~/hosts\n",
+        )
+        .unwrap();
+
+        let config = Config::load(&config_file).unwrap();
+        let srcs: Vec<&str> = config.groups[0]
+            .values
+            .iter()
+            .filter_map(|value| value.link.as_ref())
+            .map(|link| link.src.as_str())
+            .collect();
+        let dir = fs::canonicalize(dir.path()).unwrap();
+        assert_eq!(
+            vec![dir.join("vimrc").to_str().unwrap(), "/etc/hosts"],
+            srcs
+        );
+    }
+
+    #[test]
+    fn test_load_missing_src() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_file = dir.path().join("dotfiles.yml");
+        fs::write(&config_file, "dev:\n  - link: missing -> ~/.vimrc\n").unwrap();
+
+        assert!(Config::load(&config_file).is_ok());
+        match Config::check(&config_file) {
+            Err(Error::Invalid(problems)) => {
+                assert_eq!(1, problems.len());
+                assert!(problems[0].warning);
+            }
+            result => panic!("Expected missing source to fail check: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_malformed_link_shorthand() {
+        let content = r#"
+dev:
+  - link: "~/.dotfiles/git/gitconfig ~/.gitconfig"
+"#;
+        let err = Config::new(content).unwrap_err().to_string();
+        assert!(err.contains("expected \"<src> -> <dst>\""), "{}", err);
+    }
+
+    #[test]
+    fn test_empty_package_shorthand() {
+        let content = "
+dev:
+  - package: [tmux, '']
+";
+        let err = Config::new(content).unwrap_err().to_string();
+        assert!(err.contains("package name can not be empty"), "{}", err);
     }
 }
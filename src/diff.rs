@@ -0,0 +1,122 @@
+use crate::symlink::{self, Error, Status, SymLink};
+use similar::TextDiff;
+use std::fs;
+use std::path::Path;
+
+/// Unified diff from each destination of link that conflicts with a file
+/// to its source, showing what would change by relinking it. Binary files
+/// and directories are only reported as different, and destinations with
+/// the same content of their source are not included.
+pub fn diff(link: &SymLink) -> Result<Vec<String>, Error> {
+    let (src_root, dst_root) = link.paths()?;
+    let mut diffs = vec![];
+    for (dst, status) in symlink::status(link)? {
+        if status != Status::Conflict {
+            continue;
+        }
+        // Files of directory links have the same relative path in source.
+        let src = match dst.strip_prefix(&dst_root) {
+            Ok(relative) if !relative.as_os_str().is_empty() => src_root.join(relative),
+            _ => src_root.clone(),
+        };
+        if let Some(diff) = diff_files(&dst, &src)? {
+            diffs.push(diff);
+        }
+    }
+    Ok(diffs)
+}
+
+fn diff_files(dst: &Path, src: &Path) -> Result<Option<String>, Error> {
+    if dst.is_dir() || src.is_dir() {
+        return Ok(Some(format!(
+            "{} and {} are not both files\n",
+            dst.display(),
+            src.display()
+        )));
+    }
+
+    let old = fs::read(dst)?;
+    let new = fs::read(src)?;
+    if old == new {
+        return Ok(None);
+    }
+    match (text(&old), text(&new)) {
+        (Some(old), Some(new)) => Ok(Some(
+            TextDiff::from_lines(old, new)
+                .unified_diff()
+                .header(&dst.display().to_string(), &src.display().to_string())
+                .to_string(),
+        )),
+        _ => Ok(Some(format!(
+            "Binary files {} and {} differ\n",
+            dst.display(),
+            src.display()
+        ))),
+    }
+}
+
+// Content as text, if it is valid UTF-8 without NUL bytes.
+fn text(content: &[u8]) -> Option<&str> {
+    if content.contains(&0) {
+        return None;
+    }
+    std::str::from_utf8(content).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_diff() {
+        let src = tempdir().unwrap();
+        let dst = tempdir().unwrap();
+        let write = |dir: &Path, name: &str, content: &[u8]| {
+            fs::write(dir.join(name), content).unwrap();
+        };
+        write(src.path(), "text", b"foo\nbar\n");
+        write(dst.path(), "text", b"foo\nbaz\n");
+        write(src.path(), "same", b"foo\n");
+        write(dst.path(), "same", b"foo\n");
+        write(src.path(), "binary", b"\0\x01");
+        write(dst.path(), "binary", b"\0\x02");
+        write(src.path(), "missing", b"foo\n");
+
+        let link = |name: &str| {
+            SymLink::new(
+                dst.path().join(name).to_str().unwrap(),
+                src.path().join(name).to_str().unwrap(),
+                false,
+                true,
+            )
+        };
+
+        assert_eq!(
+            vec![format!(
+                "--- {}\n+++ {}\n@@ -1,2 +1,2 @@\n foo\n-baz\n+bar\n",
+                dst.path().join("text").display(),
+                src.path().join("text").display()
+            )],
+            diff(&link("text")).unwrap()
+        );
+        assert!(diff(&link("same")).unwrap().is_empty());
+        assert!(diff(&link("missing")).unwrap().is_empty());
+        assert_eq!(
+            vec![format!(
+                "Binary files {} and {} differ\n",
+                dst.path().join("binary").display(),
+                src.path().join("binary").display()
+            )],
+            diff(&link("binary")).unwrap()
+        );
+
+        let dir = SymLink::new(
+            dst.path().to_str().unwrap(),
+            src.path().to_str().unwrap(),
+            false,
+            true,
+        );
+        assert_eq!(2, diff(&dir).unwrap().len());
+    }
+}
@@ -0,0 +1,400 @@
+use crate::{
+    adopt,
+    config::{self, Config},
+    pacman::Package,
+};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Files of home that are usually kept in a dotfiles repository.
+pub const DOTFILES: &[&str] = &[
+    ".bashrc",
+    ".bash_profile",
+    ".profile",
+    ".zshrc",
+    ".gitconfig",
+    ".vimrc",
+    ".tmux.conf",
+    ".inputrc",
+];
+
+/// Group of starter config with the packages.
+pub const PACKAGES_GROUP: &str = "base";
+
+/// Group of starter config with the links of adopted dotfiles.
+pub const DOTFILES_GROUP: &str = "dotfiles";
+
+#[derive(Debug)]
+pub enum Error {
+    /// Config file already exists
+    Exists(PathBuf),
+
+    /// Starter config is not valid
+    Config(config::Error),
+
+    /// Dotfile can not be adopted
+    Adopt(adopt::Error),
+
+    /// Error adopting a dotfile after the others were already adopted
+    Partial {
+        adopted: Vec<PathBuf>,
+        error: adopt::Error,
+    },
+
+    /// Included name is neither an installed package nor a file of home
+    Unknown(String),
+
+    /// io error creating the dotfiles directory
+    Io(io::Error),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Exists(path) => write!(f, "Config file {:?} already exists", path),
+            Error::Config(err) => err.fmt(f),
+            Error::Adopt(err) => err.fmt(f),
+            Error::Partial { adopted, error } => {
+                let adopted: Vec<String> =
+                    adopted.iter().map(|p| p.display().to_string()).collect();
+                write!(f, "{}, already adopted: {}", error, adopted.join(", "))
+            }
+            Error::Unknown(name) => write!(
+                f,
+                "{} is neither an installed package nor a file of home",
+                name
+            ),
+            Error::Io(err) => err.fmt(f),
+        }
+    }
+}
+
+impl From<config::Error> for Error {
+    fn from(val: config::Error) -> Self {
+        Error::Config(val)
+    }
+}
+
+impl From<adopt::Error> for Error {
+    fn from(val: adopt::Error) -> Self {
+        Error::Adopt(val)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(val: io::Error) -> Self {
+        Error::Io(val)
+    }
+}
+
+/// Which of the found packages and dotfiles are included in the starter config.
+#[derive(Debug, Default, PartialEq)]
+pub struct Selection {
+    /// Include all packages installed explicitly.
+    pub packages: bool,
+
+    /// Include all dotfiles found in home.
+    pub dotfiles: bool,
+
+    /// Package names or dotfiles, relative to home, included anyway.
+    pub include: Vec<String>,
+
+    /// Package names or dotfiles, relative to home, never included.
+    pub exclude: Vec<String>,
+}
+
+impl Selection {
+    pub fn package(&self, package: &Package) -> bool {
+        self.selected(self.packages, &package.name)
+    }
+
+    pub fn dotfile(&self, dotfile: &Path) -> bool {
+        self.selected(self.dotfiles, &dotfile.to_string_lossy())
+    }
+
+    /// Check if the installed packages are needed to select: to include all
+    /// of them, or to tell if an included name that is not a file of home
+    /// is a package.
+    pub fn needs_packages(&self, home: &Path) -> bool {
+        self.packages
+            || self.include.iter().any(|name| {
+                home.join(name.trim_end_matches('/'))
+                    .symlink_metadata()
+                    .is_err()
+            })
+    }
+
+    /// Dotfiles selected among the found ones, and the included dotfiles of
+    /// home that were not found, like `.ssh/config`. Included names that are
+    /// neither one of the installed packages nor a file of home are an error.
+    pub fn dotfiles(
+        &self,
+        found: &[PathBuf],
+        packages: &[Package],
+        home: &Path,
+    ) -> Result<Vec<PathBuf>, Error> {
+        let mut dotfiles: Vec<PathBuf> =
+            found.iter().filter(|d| self.dotfile(d)).cloned().collect();
+        for name in self.include.iter() {
+            let dotfile = PathBuf::from(name.trim_end_matches('/'));
+            if found.contains(&dotfile) || packages.iter().any(|p| &p.name == name) {
+                continue;
+            }
+            if home.join(&dotfile).symlink_metadata().is_err() {
+                return Err(Error::Unknown(name.clone()));
+            }
+            if self.dotfile(&dotfile) {
+                dotfiles.push(dotfile);
+            }
+        }
+        Ok(dotfiles)
+    }
+
+    fn selected(&self, all: bool, name: &str) -> bool {
+        let listed = |names: &[String]| names.iter().any(|n| n.trim_end_matches('/') == name);
+        (all || listed(&self.include)) && !listed(&self.exclude)
+    }
+}
+
+/// Dotfiles found in home, relative to it: the well known `DOTFILES` and
+/// everything inside `~/.config`. Symbolic links are not included, since
+/// they are probably already managed.
+pub fn dotfiles(home: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut found: Vec<PathBuf> = DOTFILES.iter().map(PathBuf::from).collect();
+    match fs::read_dir(home.join(".config")) {
+        Ok(entries) => {
+            let mut config = vec![];
+            for entry in entries {
+                config.push(Path::new(".config").join(entry?.file_name()));
+            }
+            config.sort();
+            found.extend(config);
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(err) => return Err(err),
+    }
+    Ok(found
+        .into_iter()
+        .filter(|path| match home.join(path).symlink_metadata() {
+            Ok(metadata) => !metadata.file_type().is_symlink(),
+            Err(_) => false,
+        })
+        .collect())
+}
+
+/// Content of the starter config, with the packages in the `base` group.
+/// Git is always included, since it is needed to clone the dotfiles.
+pub fn config(packages: &[Package]) -> String {
+    let mut content = String::from(
+        "# Config of ipa, see https://github.com/msAlcantara/ipa#configuration
+#
+# Values are configured by group, each one can have a package, a link and a
+# shell command, like:
+#
+#   - package: neovim
+#     link: ~/.dotfiles/.config/nvim -> ~/.config/nvim
+#   - shell: echo configured
+
+",
+    );
+    content.push_str(&format!("{}:\n  - package: git\n", PACKAGES_GROUP));
+    for package in packages.iter().filter(|p| p.name != "git") {
+        content.push_str(&format!("  - package: {}\n", package.name));
+    }
+    content
+}
+
+/// Create the dotfiles directory with a starter config file, adopting each
+/// dotfile of home with the same code of `ipa adopt`. The dotfiles are
+/// checked before changing anything, and if adopting one still fails the
+/// error includes the ones that were already adopted.
+pub fn init(
+    config_file: &Path,
+    packages: &[Package],
+    dotfiles: &[PathBuf],
+    home: &Path,
+) -> Result<(), Error> {
+    if config_file.symlink_metadata().is_ok() {
+        return Err(Error::Exists(config_file.to_path_buf()));
+    }
+    let content = config(packages);
+    Config::new(&content)?;
+    let dir = config_file.parent().unwrap_or_else(|| Path::new("/"));
+    for dotfile in dotfiles.iter() {
+        check(&home.join(dotfile), &dir.join(dotfile))?;
+    }
+
+    fs::create_dir_all(dir)?;
+    fs::write(config_file, content)?;
+    for (i, dotfile) in dotfiles.iter().enumerate() {
+        match adopt::adopt(config_file, &home.join(dotfile), DOTFILES_GROUP, home) {
+            Ok(_) => {}
+            Err(error) if i == 0 => return Err(Error::Adopt(error)),
+            Err(error) => {
+                return Err(Error::Partial {
+                    adopted: dotfiles[..i].to_vec(),
+                    error,
+                })
+            }
+        }
+    }
+    Ok(())
+}
+
+// Check that path can be adopted as target, like `adopt::adopt` does,
+// without changing anything.
+fn check(path: &Path, target: &Path) -> Result<(), Error> {
+    let metadata = path
+        .symlink_metadata()
+        .map_err(|err| Error::Adopt(adopt::Error::Io(err)))?;
+    if metadata.file_type().is_symlink() {
+        return Err(Error::Adopt(adopt::Error::AlreadyLink(path.to_path_buf())));
+    }
+    if target.symlink_metadata().is_ok() {
+        return Err(Error::Adopt(adopt::Error::Exists(target.to_path_buf())));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_selection() {
+        let selection = Selection {
+            packages: true,
+            dotfiles: false,
+            include: vec![String::from(".config/nvim/")],
+            exclude: vec![String::from("go")],
+        };
+        assert!(selection.package(&Package::new("git")));
+        assert!(!selection.package(&Package::new("go")));
+        assert!(selection.dotfile(Path::new(".config/nvim")));
+        assert!(!selection.dotfile(Path::new(".bashrc")));
+    }
+
+    #[test]
+    fn test_selection_needs_packages() {
+        let home = tempdir().unwrap();
+        fs::create_dir_all(home.path().join(".config/nvim")).unwrap();
+        fs::write(home.path().join(".bashrc"), "").unwrap();
+
+        let mut selection = Selection {
+            include: vec![String::from(".bashrc"), String::from(".config/nvim/")],
+            ..Selection::default()
+        };
+        assert!(!selection.needs_packages(home.path()));
+        selection.include.push(String::from("git"));
+        assert!(selection.needs_packages(home.path()));
+
+        let selection = Selection {
+            packages: true,
+            ..Selection::default()
+        };
+        assert!(selection.needs_packages(home.path()));
+    }
+
+    #[test]
+    fn test_selection_dotfiles() {
+        let home = tempdir().unwrap();
+        fs::create_dir_all(home.path().join(".ssh")).unwrap();
+        fs::write(home.path().join(".ssh/config"), "Host *").unwrap();
+
+        let found = vec![PathBuf::from(".bashrc"), PathBuf::from(".config/nvim")];
+        let packages = vec![Package::new("git")];
+        let mut selection = Selection {
+            include: vec![
+                String::from(".bashrc"),
+                String::from("git"),
+                String::from(".ssh/config"),
+            ],
+            ..Selection::default()
+        };
+        assert_eq!(
+            vec![PathBuf::from(".bashrc"), PathBuf::from(".ssh/config")],
+            selection.dotfiles(&found, &packages, home.path()).unwrap()
+        );
+
+        selection.include.push(String::from(".missing"));
+        assert!(matches!(
+            selection.dotfiles(&found, &packages, home.path()),
+            Err(Error::Unknown(name)) if name == ".missing"
+        ));
+    }
+
+    #[test]
+    fn test_init_checks_dotfiles() {
+        let home = tempdir().unwrap();
+        fs::write(home.path().join(".bashrc"), "alias l=ls").unwrap();
+        fs::write(home.path().join(".vimrc"), "set nu").unwrap();
+        fs::create_dir_all(home.path().join(".dotfiles")).unwrap();
+        fs::write(home.path().join(".dotfiles/.vimrc"), "set rnu").unwrap();
+
+        let config_file = home.path().join(".dotfiles/dotfiles.yml");
+        let dotfiles = vec![PathBuf::from(".bashrc"), PathBuf::from(".vimrc")];
+        assert!(matches!(
+            init(&config_file, &[], &dotfiles, home.path()),
+            Err(Error::Adopt(adopt::Error::Exists(_)))
+        ));
+        assert!(!config_file.exists());
+        assert!(home.path().join(".bashrc").is_file());
+        assert!(!home.path().join(".dotfiles/.bashrc").exists());
+
+        let err = Error::Partial {
+            adopted: vec![PathBuf::from(".bashrc")],
+            error: adopt::Error::AlreadyLink(PathBuf::from("/home/.vimrc")),
+        };
+        assert_eq!(
+            "\"/home/.vimrc\" is already a symbolic link, already adopted: .bashrc",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn test_init() {
+        let home = tempdir().unwrap();
+        fs::create_dir_all(home.path().join(".config/nvim")).unwrap();
+        fs::write(home.path().join(".config/nvim/init.vim"), "set nu").unwrap();
+        fs::write(home.path().join(".bashrc"), "alias l=ls").unwrap();
+        std::os::unix::fs::symlink("/tmp", home.path().join(".config/linked")).unwrap();
+
+        let found = dotfiles(home.path()).unwrap();
+        assert_eq!(
+            vec![PathBuf::from(".bashrc"), PathBuf::from(".config/nvim")],
+            found
+        );
+
+        let config_file = home.path().join(".dotfiles/dotfiles.yml");
+        let packages = vec![Package::new("git"), Package::new("neovim")];
+        init(&config_file, &packages, &found, home.path()).unwrap();
+
+        let content = fs::read_to_string(&config_file).unwrap();
+        assert!(content.ends_with(
+            "base:
+  - package: git
+  - package: neovim
+
+dotfiles:
+  - link: ~/.dotfiles/.bashrc -> ~/.bashrc
+  - link: ~/.dotfiles/.config/nvim -> ~/.config/nvim
+"
+        ));
+        assert!(Config::new(&content).is_ok());
+        assert!(home
+            .path()
+            .join(".bashrc")
+            .symlink_metadata()
+            .unwrap()
+            .file_type()
+            .is_symlink());
+
+        assert!(matches!(
+            init(&config_file, &packages, &[], home.path()),
+            Err(Error::Exists(_))
+        ));
+    }
+}
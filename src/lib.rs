@@ -1,6 +1,16 @@
+pub mod adopt;
+pub mod atomic;
 pub mod cli;
 pub mod config;
+pub mod diff;
+pub mod init;
+pub mod list;
+pub mod packages;
 pub mod pacman;
+pub mod privilege;
 pub mod runner;
 pub mod shell;
+pub mod state;
+pub mod status;
 pub mod symlink;
+pub mod validate;
@@ -0,0 +1,212 @@
+use crate::{config::Config, runner::Error};
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// Format used to print the groups of config.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            _ => Err(format!("unknown format {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct GroupEntries {
+    pub name: String,
+
+    /// Number of values of group.
+    pub entries: usize,
+
+    /// Values of group, only when listing the entries.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub values: Option<Vec<Entry>>,
+}
+
+/// Value of group with its links expanded.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct Entry {
+    pub id: String,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub packages: Vec<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link: Option<Link>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shell: Option<String>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct Link {
+    pub src: PathBuf,
+    pub dst: PathBuf,
+}
+
+/// Groups of config with the number of values of each one. With `entries`,
+/// or when listing a single group, the values of groups are included too.
+/// Links whose paths can not be expanded, like with an unset variable, are
+/// listed as written in config.
+pub fn list(
+    config: &Config,
+    group: Option<&str>,
+    entries: bool,
+) -> Result<Vec<GroupEntries>, Error> {
+    if let Some(group) = group {
+        if config.group(group).is_none() {
+            return Err(Error::InvalidGroup(group.to_string()));
+        }
+    }
+
+    let mut groups = vec![];
+    for g in config.groups.iter() {
+        if group.is_some_and(|name| name != g.name) {
+            continue;
+        }
+        let values = match entries || group.is_some() {
+            true => Some(
+                g.values
+                    .iter()
+                    .map(|value| Entry {
+                        id: value.id(),
+                        packages: value.packages.iter().map(|p| p.name.clone()).collect(),
+                        link: value.link.as_ref().map(|link| {
+                            let (src, dst) = link.paths().unwrap_or_else(|_| {
+                                (PathBuf::from(&link.src), PathBuf::from(&link.dst))
+                            });
+                            Link { src, dst }
+                        }),
+                        shell: value.shell.as_ref().map(|shell| shell.command_line()),
+                    })
+                    .collect(),
+            ),
+            false => None,
+        };
+        groups.push(GroupEntries {
+            name: g.name.clone(),
+            entries: g.values.len(),
+            values,
+        });
+    }
+    Ok(groups)
+}
+
+/// Render the groups in format.
+pub fn render(groups: &[GroupEntries], format: Format) -> String {
+    if format == Format::Json {
+        return serde_json::to_string_pretty(groups).unwrap_or_default() + "\n";
+    }
+
+    let width = groups
+        .iter()
+        .map(|g| g.name.len())
+        .max()
+        .unwrap_or_default();
+    let mut text = String::new();
+    for group in groups.iter() {
+        text.push_str(&format!(
+            "{:width$}  {} {}\n",
+            group.name,
+            group.entries,
+            if group.entries == 1 {
+                "entry"
+            } else {
+                "entries"
+            },
+            width = width
+        ));
+        for entry in group.values.iter().flatten() {
+            text.push_str(&format!("  {}\n", entry.id));
+            if !entry.packages.is_empty() {
+                text.push_str(&format!("    package: {}\n", entry.packages.join(", ")));
+            }
+            if let Some(ref link) = entry.link {
+                text.push_str(&format!(
+                    "    link: {} -> {}\n",
+                    link.src.display(),
+                    link.dst.display()
+                ));
+            }
+            if let Some(ref shell) = entry.shell {
+                text.push_str(&format!("    shell: {}\n", shell));
+            }
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTENT: &str = "
+base:
+  - package: [git, htop]
+
+dev:
+  - name: editor
+    package: neovim
+    link: /dotfiles/nvim -> /home/nvim
+  - shell: echo foo
+";
+
+    #[test]
+    fn test_list() {
+        let config = Config::new(CONTENT).unwrap();
+        let groups = list(&config, None, false).unwrap();
+        assert_eq!(
+            "base  1 entry\ndev   2 entries\n",
+            render(&groups, Format::Text)
+        );
+
+        let groups = list(&config, Some("dev"), false).unwrap();
+        assert_eq!(
+            "dev  2 entries
+  editor
+    package: neovim
+    link: /dotfiles/nvim -> /home/nvim
+  echo foo
+    shell: echo foo
+",
+            render(&groups, Format::Text)
+        );
+
+        assert!(matches!(
+            list(&config, Some("gui"), false),
+            Err(Error::InvalidGroup(_))
+        ));
+
+        let config = Config::new("dev:\n  - link: $IPA_LIST_UNSET/nvim -> /home/nvim\n").unwrap();
+        let groups = list(&config, Some("dev"), false).unwrap();
+        assert_eq!(
+            "dev  1 entry
+  /home/nvim
+    link: $IPA_LIST_UNSET/nvim -> /home/nvim
+",
+            render(&groups, Format::Text)
+        );
+    }
+
+    #[test]
+    fn test_list_json() {
+        let config = Config::new(CONTENT).unwrap();
+        let groups = list(&config, None, true).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&render(&groups, Format::Json)).unwrap();
+        assert_eq!(2, json[1]["entries"]);
+        assert_eq!("editor", json[1]["values"][0]["id"]);
+        assert_eq!("/home/nvim", json[1]["values"][0]["link"]["dst"]);
+        assert_eq!("git", json[0]["values"][0]["packages"][0]);
+        assert!(json[0]["values"][0].get("shell").is_none());
+    }
+}
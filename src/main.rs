@@ -1,10 +1,18 @@
 use ipa::{
-    cli::Options,
+    adopt,
+    cli::{Command, Options},
     config::Config,
-    pacman::Pacman,
-    runner::{Error, Ipa},
+    diff,
+    init::{self, Selection},
+    list::{self, Format},
+    packages,
+    pacman::{PackageManagement, Pacman},
+    privilege::{self, Escalation},
+    runner::{Error, Ipa, Summary},
+    status,
 };
 use log::info;
+use std::path::{Path, PathBuf};
 
 fn main() {
     let options = Options::new();
@@ -14,7 +22,20 @@ fn main() {
         std::process::exit(1);
     }
 
-    let config = match Config::load(options.config_file.as_path()) {
+    if let Command::Init {
+        ref selection,
+        list,
+    } = options.command
+    {
+        init(&options, selection, list);
+        return;
+    }
+
+    let load = match options.command {
+        Command::Check => Config::check,
+        _ => Config::load,
+    };
+    let config = match load(options.config_file.as_path()) {
         Ok(config) => config,
         Err(err) => {
             eprintln!("Error: {}", err);
@@ -22,11 +43,205 @@ fn main() {
         }
     };
 
-    if let Err(err) = run(options, config) {
-        eprintln!("Unrecoverable error: {}", err);
+    match options.command {
+        Command::Apply => apply(&options, config),
+        Command::Check => info!("Config file {:?} is valid", options.config_file),
+        Command::Status => status(config),
+        Command::Diff => diff(config),
+        Command::Adopt {
+            ref path,
+            ref group,
+        } => adopt(&options, path, group),
+        Command::List {
+            ref group,
+            entries,
+            format,
+        } => list(&config, group.as_deref(), entries, format),
+        Command::ExportPackages { ref group } => export_packages(&options, &config, group),
+        Command::DiffPackages => diff_packages(&config),
+        Command::Init { .. } => unreachable!("init does not load the config"),
+    }
+}
+
+fn apply(options: &Options, config: Config) {
+    if options.diff {
+        return preview(options, config);
+    }
+    if privilege::is_root() && !options.allow_root {
+        eprintln!("Error: ipa should run as a normal user, packages are installed using {}. Use --allow-root to run it as root anyway.", options.become_command);
         std::process::exit(1);
     }
-    info!("Finish with successfull, see you next time.");
+
+    match run(options, config) {
+        Ok(summary) => log_summary(&summary),
+        Err(Error::Failures(summary)) => {
+            log_summary(&summary);
+            eprintln!("{}", Error::Failures(summary));
+            std::process::exit(1);
+        }
+        Err(err) => {
+            eprintln!("Unrecoverable error: {}", err);
+            std::process::exit(1);
+        }
+    }
+}
+
+// Print the diffs of the conflicting link destinations of the selected
+// values, without changing anything.
+fn preview(options: &Options, config: Config) {
+    let pacman = Pacman::new();
+    let ipa = Ipa::new(config, &pacman)
+        .with_requires(!options.no_requires)
+        .with_tags(&options.tags)
+        .with_skip_tags(&options.skip_tags);
+    match ipa.diff_selection(&options.only, &options.except) {
+        Ok(diffs) => diffs.iter().for_each(|diff| print!("{}", diff)),
+        Err(err) => {
+            eprintln!("Unrecoverable error: {}", err);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn status(config: Config) {
+    let checks = status::check(&config, &Pacman::new());
+    // SAFETY: isatty only reads the file descriptor.
+    let color = unsafe { libc::isatty(libc::STDOUT_FILENO) } == 1;
+    print!("{}", status::render(&checks, color));
+    if checks.iter().any(|check| !check.in_sync) {
+        std::process::exit(1);
+    }
+}
+
+fn diff(config: Config) {
+    for group in config.groups.iter() {
+        for link in group.values.iter().filter_map(|value| value.link.as_ref()) {
+            match diff::diff(link) {
+                Ok(diffs) => diffs.iter().for_each(|diff| print!("{}", diff)),
+                Err(err) => {
+                    eprintln!("Unrecoverable error: {}", err);
+                    std::process::exit(1);
+                }
+            }
+        }
+    }
+}
+
+fn home() -> PathBuf {
+    match std::env::var_os("HOME") {
+        Some(home) => PathBuf::from(home),
+        None => {
+            eprintln!("Error: HOME is not set");
+            std::process::exit(1);
+        }
+    }
+}
+
+fn init(options: &Options, selection: &Selection, list: bool) {
+    let home = home();
+    let dotfiles = match init::dotfiles(&home) {
+        Ok(dotfiles) => dotfiles,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            std::process::exit(1);
+        }
+    };
+    // Listing packages depends on pacman, so it is only done when needed.
+    let packages = if list || selection.needs_packages(&home) {
+        match Pacman::new().list_installed() {
+            Ok(packages) => packages,
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        vec![]
+    };
+
+    if list {
+        packages.iter().for_each(|p| println!("package {}", p.name));
+        dotfiles
+            .iter()
+            .for_each(|d| println!("dotfile {}", d.display()));
+        return;
+    }
+
+    let dotfiles = match selection.dotfiles(&dotfiles, &packages, &home) {
+        Ok(dotfiles) => dotfiles,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            std::process::exit(1);
+        }
+    };
+    let packages: Vec<_> = packages
+        .into_iter()
+        .filter(|p| selection.package(p))
+        .collect();
+    match init::init(&options.config_file, &packages, &dotfiles, &home) {
+        Ok(()) => info!(
+            "Created {:?} with {} package(s) and {} dotfile(s)",
+            options.config_file,
+            packages.len(),
+            dotfiles.len()
+        ),
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn adopt(options: &Options, path: &Path, group: &str) {
+    let home = home();
+    match adopt::adopt(&options.config_file, path, group, &home) {
+        Ok(link) => info!("Adopted {} as {} in group {}", link.dst, link.src, group),
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn list(config: &Config, group: Option<&str>, entries: bool, format: Format) {
+    match list::list(config, group, entries) {
+        Ok(groups) => print!("{}", list::render(&groups, format)),
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn export_packages(options: &Options, config: &Config, group: &str) {
+    match packages::export(&options.config_file, config, &Pacman::new(), group) {
+        Ok(packages) => info!("Added {} package(s) to group {}", packages.len(), group),
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn diff_packages(config: &Config) {
+    match packages::diff(config, &Pacman::new()) {
+        Ok(diff) => {
+            print!("{}", diff);
+            if !diff.is_empty() {
+                std::process::exit(1);
+            }
+        }
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn log_summary(summary: &Summary) {
+    for line in summary.to_string().lines() {
+        info!("{}", line);
+    }
 }
 
 fn init_logger(options: &Options) -> Result<(), log::SetLoggerError> {
@@ -38,17 +253,15 @@ fn init_logger(options: &Options) -> Result<(), log::SetLoggerError> {
         .init()
 }
 
-fn run(options: Options, config: Config) -> Result<(), Error> {
-    let pacman = Pacman::new();
-    let ipa = Ipa::new(config, &pacman);
-
-    if let Some(only_group) = options.only_group {
-        return ipa.setup_group(&only_group);
-    }
-
-    if let Some(except_group) = options.except_group {
-        return ipa.setup_except_group(&except_group);
-    }
+fn run(options: &Options, config: Config) -> Result<Summary, Error> {
+    let escalation = Escalation::new(&options.become_command);
+    let pacman = Pacman::new().with_escalation(escalation.clone());
+    let ipa = Ipa::new(config, &pacman)
+        .with_requires(!options.no_requires)
+        .with_keep_going(options.keep_going)
+        .with_tags(&options.tags)
+        .with_skip_tags(&options.skip_tags)
+        .with_escalation(escalation);
 
-    ipa.setup()
+    ipa.setup_selection(&options.only, &options.except)
 }
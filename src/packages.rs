@@ -0,0 +1,212 @@
+use crate::{
+    adopt, atomic,
+    config::{self, Config},
+    pacman::{self, Package, PackageManagement},
+};
+use std::{fs, io, path::Path};
+
+#[derive(Debug)]
+pub enum Error {
+    /// Error listing the installed packages
+    Pacman(pacman::Error),
+
+    /// Config file is not valid after adding the entries
+    Config(config::Error),
+
+    /// Error adding the entries to config file
+    Edit(adopt::Error),
+
+    /// io error writing config file
+    Io(io::Error),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Pacman(err) => err.fmt(f),
+            Error::Config(err) => err.fmt(f),
+            Error::Edit(err) => err.fmt(f),
+            Error::Io(err) => err.fmt(f),
+        }
+    }
+}
+
+impl From<pacman::Error> for Error {
+    fn from(val: pacman::Error) -> Self {
+        Error::Pacman(val)
+    }
+}
+
+impl From<config::Error> for Error {
+    fn from(val: config::Error) -> Self {
+        Error::Config(val)
+    }
+}
+
+impl From<adopt::Error> for Error {
+    fn from(val: adopt::Error) -> Self {
+        Error::Edit(val)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(val: io::Error) -> Self {
+        Error::Io(val)
+    }
+}
+
+/// Differences between the packages of config and the packages installed
+/// on the machine.
+#[derive(Debug, Default, PartialEq)]
+pub struct Diff {
+    /// Installed explicitly on the machine, but missing from config.
+    pub unmanaged: Vec<String>,
+
+    /// Declared in config, but not installed on the machine.
+    pub uninstalled: Vec<String>,
+}
+
+impl Diff {
+    pub fn is_empty(&self) -> bool {
+        self.unmanaged.is_empty() && self.uninstalled.is_empty()
+    }
+}
+
+impl std::fmt::Display for Diff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (title, names) in [
+            ("Installed but not in config:", &self.unmanaged),
+            ("In config but not installed:", &self.uninstalled),
+        ] {
+            if names.is_empty() {
+                continue;
+            }
+            writeln!(f, "{}", title)?;
+            for name in names.iter() {
+                writeln!(f, "  {}", name)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Compare the packages of every group of config with the installed ones.
+/// Packages of config installed as dependencies of others are installed too.
+pub fn diff<P>(config: &Config, pacman: &P) -> Result<Diff, Error>
+where
+    P: PackageManagement,
+{
+    let declared = declared(config);
+    let unmanaged = pacman
+        .list_installed()?
+        .into_iter()
+        .map(|p| p.name)
+        .filter(|name| !declared.contains(name))
+        .collect();
+    let mut uninstalled = vec![];
+    for name in declared.into_iter() {
+        if !pacman.installed(&Package::new(&name))? {
+            uninstalled.push(name);
+        }
+    }
+    Ok(Diff {
+        unmanaged,
+        uninstalled,
+    })
+}
+
+/// Add the packages installed but missing from config to group, as `package`
+/// entries, keeping the formatting and comments of config file. Returns the
+/// packages that were added.
+pub fn export<P>(
+    config_file: &Path,
+    config: &Config,
+    pacman: &P,
+    group: &str,
+) -> Result<Vec<Package>, Error>
+where
+    P: PackageManagement,
+{
+    let declared = declared(config);
+    let packages: Vec<Package> = pacman
+        .list_installed()?
+        .into_iter()
+        .filter(|package| !declared.contains(&package.name))
+        .collect();
+    if packages.is_empty() {
+        return Ok(packages);
+    }
+
+    let mut content = fs::read_to_string(config_file)?;
+    for package in packages.iter() {
+        content = adopt::add_entry(&content, group, &format!("package: {}", package.name))?;
+    }
+    Config::new(&content)?;
+    atomic::write(config_file, content.as_bytes())?;
+    Ok(packages)
+}
+
+// Names of packages declared in any group of config, without duplicates.
+fn declared(config: &Config) -> Vec<String> {
+    let mut names: Vec<String> = vec![];
+    for group in config.groups.iter() {
+        for package in group.values.iter().flat_map(|value| value.packages.iter()) {
+            if !names.contains(&package.name) {
+                names.push(package.name.clone());
+            }
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pacman::FakePacman;
+    use tempfile::tempdir;
+
+    // openssl is installed as a dependency, so it is not listed.
+    fn fake_pacman() -> FakePacman {
+        FakePacman::with_installed(&["git", "neovim", "tmux"], &["openssl"])
+    }
+
+    const CONTENT: &str = "base:
+  - package: git # version control
+  - package: [htop, openssl]
+
+dev:
+  - link: a -> b
+";
+
+    #[test]
+    fn test_diff() {
+        let config = Config::new(CONTENT).unwrap();
+        let diff = diff(&config, &fake_pacman()).unwrap();
+        assert_eq!(
+            Diff {
+                unmanaged: vec![String::from("neovim"), String::from("tmux")],
+                uninstalled: vec![String::from("htop")],
+            },
+            diff
+        );
+        assert_eq!(
+            "Installed but not in config:\n  neovim\n  tmux\nIn config but not installed:\n  htop\n",
+            diff.to_string()
+        );
+    }
+
+    #[test]
+    fn test_export() {
+        let dir = tempdir().unwrap();
+        let config_file = dir.path().join("dotfiles.yml");
+        fs::write(&config_file, CONTENT).unwrap();
+        let config = Config::new(CONTENT).unwrap();
+
+        let packages = export(&config_file, &config, &fake_pacman(), "dev").unwrap();
+        assert_eq!(vec![Package::new("neovim"), Package::new("tmux")], packages);
+        assert_eq!(
+            format!("{}  - package: neovim\n  - package: tmux\n", CONTENT),
+            fs::read_to_string(&config_file).unwrap()
+        );
+    }
+}
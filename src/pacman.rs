@@ -1,13 +1,19 @@
+use crate::privilege::Escalation;
 use log::{debug, warn};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::io;
 use std::process::{Command, Stdio};
+use std::str::FromStr;
 
 #[derive(Debug)]
 pub enum Error {
     /// Error installing package
     PacmanSync(String),
 
+    /// Error listing the installed packages
+    PacmanQuery,
+
     /// io error installing package.
     Io(io::Error),
 }
@@ -16,6 +22,7 @@ impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             Error::PacmanSync(p) => write!(f, "Unable to install package {}", p),
+            Error::PacmanQuery => write!(f, "Unable to list installed packages"),
             Error::Io(e) => write!(f, "{}", e),
         }
     }
@@ -28,8 +35,8 @@ impl From<io::Error> for Error {
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
 pub struct Package {
-    #[serde(default)]
     pub name: String,
 }
 
@@ -41,22 +48,54 @@ impl Package {
     }
 }
 
+impl FromStr for Package {
+    type Err = String;
+
+    /// Parse the shorthand form `package: tmux`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim();
+        if name.is_empty() {
+            return Err(String::from("package name can not be empty"));
+        }
+        Ok(Package::new(name))
+    }
+}
+
 pub trait PackageManagement {
-    fn install(&self, package: &Package) -> Result<(), Error>;
+    /// Install the package, returning false if it was already installed.
+    fn install(&self, package: &Package) -> Result<bool, Error>;
+
+    /// Check if the package is installed, explicitly or as a dependency of
+    /// others, without installing it.
+    fn installed(&self, package: &Package) -> Result<bool, Error>;
+
+    /// List the packages installed explicitly, not as dependencies of others.
+    fn list_installed(&self) -> Result<Vec<Package>, Error>;
 }
 
 pub struct Pacman {
     bin: &'static str,
+    escalation: Escalation,
 }
 
 impl Pacman {
     pub fn new() -> Self {
-        Pacman { bin: "pacman" }
+        Pacman {
+            bin: "pacman",
+            escalation: Escalation::default(),
+        }
+    }
+
+    /// Set the command used to install packages as root.
+    pub fn with_escalation(mut self, escalation: Escalation) -> Self {
+        self.escalation = escalation;
+        self
     }
 
+    /// Check if the package is installed, explicitly or as a dependency of others.
     pub fn is_installed(&self, package: &str) -> std::io::Result<bool> {
         Ok(Command::new(self.bin)
-            .arg("-Qe")
+            .arg("-Q")
             .arg(package)
             .stdout(Stdio::null())
             .stderr(Stdio::null())
@@ -66,26 +105,97 @@ impl Pacman {
 }
 
 impl PackageManagement for Pacman {
-    fn install(&self, package: &Package) -> Result<(), Error> {
+    fn installed(&self, package: &Package) -> Result<bool, Error> {
+        Ok(self.is_installed(&package.name)?)
+    }
+
+    fn list_installed(&self) -> Result<Vec<Package>, Error> {
+        let output = Command::new(self.bin)
+            .arg("-Qqe")
+            .stderr(Stdio::null())
+            .output()?;
+        if !output.status.success() {
+            return Err(Error::PacmanQuery);
+        }
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter(|name| !name.is_empty())
+            .map(Package::new)
+            .collect())
+    }
+
+    fn install(&self, package: &Package) -> Result<bool, Error> {
         if self.is_installed(&package.name)? {
             warn!("Package {} already installed", package.name);
-            return Ok(());
+            return Ok(false);
         }
         debug!("Installing package {}", package.name);
-        let status = Command::new(self.bin)
-            .arg("-S")
-            .arg(&package.name)
-            .arg("--noconfirm")
-            .arg("--quiet")
+        let status = self
+            .escalation
+            .command(
+                "root",
+                &BTreeMap::new(),
+                &[self.bin, "-S", &package.name, "--noconfirm", "--quiet"],
+            )
             .stdout(Stdio::null())
             .status()?;
         if status.success() {
-            return Ok(());
+            return Ok(true);
         }
         return Err(Error::PacmanSync(package.name.clone()));
     }
 }
 
+/// Package manager that installs packages only in memory, for tests.
+#[cfg(test)]
+#[derive(Default)]
+pub struct FakePacman {
+    /// Packages installed explicitly, in the order that they were installed.
+    pub installed_packages: std::cell::RefCell<Vec<String>>,
+
+    /// Packages installed as dependencies of others, so they are not listed.
+    pub dependencies: Vec<String>,
+}
+
+#[cfg(test)]
+impl FakePacman {
+    pub fn new() -> Self {
+        FakePacman::default()
+    }
+
+    pub fn with_installed(explicit: &[&str], dependencies: &[&str]) -> Self {
+        FakePacman {
+            installed_packages: std::cell::RefCell::new(
+                explicit.iter().map(|name| name.to_string()).collect(),
+            ),
+            dependencies: dependencies.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl PackageManagement for FakePacman {
+    fn install(&self, package: &Package) -> Result<bool, Error> {
+        if self.installed(package)? {
+            return Ok(false);
+        }
+        self.installed_packages
+            .borrow_mut()
+            .push(package.name.clone());
+        Ok(true)
+    }
+
+    fn installed(&self, package: &Package) -> Result<bool, Error> {
+        Ok(self.installed_packages.borrow().contains(&package.name)
+            || self.dependencies.contains(&package.name))
+    }
+
+    fn list_installed(&self) -> Result<Vec<Package>, Error> {
+        let installed = self.installed_packages.borrow();
+        Ok(installed.iter().map(|name| Package::new(name)).collect())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -95,4 +205,18 @@ mod tests {
         let pacman = Pacman::new();
         assert!(pacman.install(&Package::new("-bla")).is_err());
     }
+
+    #[test]
+    fn test_fake_pacman() {
+        let pacman = FakePacman::with_installed(&["git"], &["openssl"]);
+        assert!(pacman.installed(&Package::new("openssl")).unwrap());
+        assert!(!pacman.installed(&Package::new("neovim")).unwrap());
+        assert!(pacman.install(&Package::new("neovim")).unwrap());
+        assert!(!pacman.install(&Package::new("git")).unwrap());
+        assert!(!pacman.install(&Package::new("openssl")).unwrap());
+        assert_eq!(
+            vec![Package::new("git"), Package::new("neovim")],
+            pacman.list_installed().unwrap()
+        );
+    }
 }
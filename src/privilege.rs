@@ -0,0 +1,87 @@
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::process::Command;
+
+/// Check if ipa is running as root.
+pub fn is_root() -> bool {
+    // SAFETY: geteuid has no preconditions and always succeeds.
+    unsafe { libc::geteuid() == 0 }
+}
+
+/// Command used to run programs as other user, like `sudo` or `doas`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Escalation {
+    program: String,
+}
+
+impl Default for Escalation {
+    fn default() -> Self {
+        Escalation::new("sudo")
+    }
+}
+
+impl Escalation {
+    pub fn new(program: &str) -> Self {
+        Escalation {
+            program: program.to_string(),
+        }
+    }
+
+    /// Create the command to execute program with args as user. The
+    /// environment variables are passed using `env`, since the escalation
+    /// command usually resets the environment. Program is executed directly
+    /// when user is root and ipa is already running as root.
+    pub fn command<S>(&self, user: &str, env: &BTreeMap<String, String>, argv: &[S]) -> Command
+    where
+        S: AsRef<OsStr>,
+    {
+        if user == "root" && is_root() {
+            let mut process = Command::new(&argv[0]);
+            process.args(&argv[1..]).envs(env.iter());
+            return process;
+        }
+        let mut process = Command::new(&self.program);
+        process.arg("-u").arg(user).arg("env");
+        process.args(env.iter().map(|(key, value)| format!("{}={}", key, value)));
+        process.args(argv);
+        process
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::Path;
+
+    // Write an escalation command that records its arguments and executes
+    // the program without changing the user.
+    pub fn stub(dir: &Path) -> Escalation {
+        let path = dir.join("stub-sudo");
+        fs::write(
+            &path,
+            "#!/bin/sh\necho \"$@\" >> \"$(dirname \"$0\")/args\"\nshift 2\nexec \"$@\"\n",
+        )
+        .unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        Escalation::new(path.to_str().unwrap())
+    }
+
+    #[test]
+    fn test_escalation_command() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut env = BTreeMap::new();
+        env.insert(String::from("FOO"), String::from("bar"));
+
+        let output = stub(dir.path())
+            .command("nobody", &env, &["sh", "-c", "echo $FOO"])
+            .output()
+            .unwrap();
+        assert_eq!("bar\n", String::from_utf8_lossy(&output.stdout));
+        assert_eq!(
+            "-u nobody env FOO=bar sh -c echo $FOO\n",
+            fs::read_to_string(dir.path().join("args")).unwrap()
+        );
+    }
+}
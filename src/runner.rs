@@ -1,13 +1,41 @@
 use crate::{
-    config::{Config, Values},
-    pacman, shell, symlink,
+    config::{self, Config, Group, Hooks, Values},
+    diff, pacman,
+    privilege::Escalation,
+    shell::{self, Shell},
+    state::{self, Kind, Lock, Record, State},
+    symlink,
 };
-use log::info;
+use log::{error, info};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 #[derive(Debug)]
 pub enum Error {
     /// Group name not exists in config
-    InvalidGroup,
+    InvalidGroup(String),
+
+    /// Value name not exists in group
+    InvalidValue(String),
+
+    /// Group pattern is not a valid glob
+    InvalidPattern(String),
+
+    /// Handler notified by a value not exists in config
+    InvalidHandler(String),
+
+    /// Groups or values that depend on each other to be configured
+    Cycle(Vec<String>),
+
+    /// Error configuring a value of group
+    Value(String, Box<Error>),
+
+    /// Value skipped because it requires a value that failed
+    Dependency(String),
+
+    /// Values that failed when keeping going on errors, with the summary of run
+    Failures(Summary),
 
     /// Error with instalation of package
     Pacman(pacman::Error),
@@ -17,15 +45,40 @@ pub enum Error {
 
     /// Error to execute shell commands
     Shell(shell::Error),
+
+    /// Error reading or writing the state of previous runs
+    State(state::Error),
 }
 
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Error::InvalidGroup => write!(f, "Invalid group name"),
+            Error::InvalidGroup(group) => write!(f, "Invalid group name {}", group),
+            Error::InvalidValue(value) => write!(f, "Invalid value name {}", value),
+            Error::InvalidPattern(pattern) => write!(f, "Invalid group pattern {}", pattern),
+            Error::InvalidHandler(handler) => write!(f, "Invalid handler name {}", handler),
+            Error::Cycle(targets) => write!(f, "Cyclic dependency: {}", targets.join(" -> ")),
+            Error::Value(id, err) => write!(f, "{}: {}", id, err),
+            Error::Dependency(target) => write!(f, "Skipped, requires {} that failed", target),
+            Error::Failures(summary) => {
+                let failures = &summary.failures;
+                write!(f, "Finished with {} failure(s):", failures.len())?;
+                let width = failures.iter().map(|f| f.target.len()).max();
+                for failure in failures.iter() {
+                    write!(
+                        f,
+                        "\n  {:width$}  {}",
+                        failure.target,
+                        failure.error,
+                        width = width.unwrap_or_default()
+                    )?;
+                }
+                Ok(())
+            }
             Error::Pacman(err) => err.fmt(f),
             Error::SymLink(err) => err.fmt(f),
             Error::Shell(err) => err.fmt(f),
+            Error::State(err) => err.fmt(f),
         }
     }
 }
@@ -42,6 +95,12 @@ impl From<pacman::Error> for Error {
     }
 }
 
+impl From<state::Error> for Error {
+    fn from(val: state::Error) -> Self {
+        Error::State(val)
+    }
+}
+
 impl From<symlink::Error> for Error {
     fn from(val: symlink::Error) -> Self {
         Error::SymLink(val)
@@ -51,6 +110,12 @@ impl From<symlink::Error> for Error {
 pub struct Ipa<'a, P: pacman::PackageManagement> {
     config: Config,
     pacman: &'a P,
+    requires: bool,
+    state_dir: Option<PathBuf>,
+    escalation: Escalation,
+    keep_going: bool,
+    tags: Vec<String>,
+    skip_tags: Vec<String>,
 }
 
 impl<'a, P> Ipa<'a, P>
@@ -58,84 +123,798 @@ where
     P: pacman::PackageManagement,
 {
     pub fn new(config: Config, pacman: &'a P) -> Self {
-        Ipa { config, pacman }
+        Ipa {
+            config,
+            pacman,
+            requires: true,
+            state_dir: state::default_dir(),
+            escalation: Escalation::default(),
+            keep_going: false,
+            tags: vec![],
+            skip_tags: vec![],
+        }
+    }
+
+    /// Set if the groups required by the selected group are also configured
+    /// by `setup_group`. Enabled by default.
+    pub fn with_requires(mut self, requires: bool) -> Self {
+        self.requires = requires;
+        self
+    }
+
+    /// Set the directory where ipa keeps the state of previous runs, like the
+    /// actions applied and the hashes of scripts. Default to `state::default_dir`.
+    pub fn with_state_dir(mut self, state_dir: &Path) -> Self {
+        self.state_dir = Some(state_dir.to_path_buf());
+        self
+    }
+
+    /// Do not keep the state of runs, so scripts with `on_change` are
+    /// always executed.
+    pub fn without_state(mut self) -> Self {
+        self.state_dir = None;
+        self
+    }
+
+    /// Set the command used to run shell commands as other user. Default to `sudo`.
+    pub fn with_escalation(mut self, escalation: Escalation) -> Self {
+        self.escalation = escalation;
+        self
+    }
+
+    /// Set if the run continues when a value fails, skipping the values that
+    /// require it and returning all failures at the end. Disabled by default.
+    pub fn with_keep_going(mut self, keep_going: bool) -> Self {
+        self.keep_going = keep_going;
+        self
+    }
+
+    /// Set the tags of values to configure, of the value itself or of its group.
+    /// Values without any of them are not configured, unless required by others.
+    pub fn with_tags(mut self, tags: &[String]) -> Self {
+        self.tags = tags.to_vec();
+        self
+    }
+
+    /// Set the tags of values to never configure, even when required by others.
+    pub fn with_skip_tags(mut self, skip_tags: &[String]) -> Self {
+        self.skip_tags = skip_tags.to_vec();
+        self
+    }
+
+    /// Configure all groups except the given one. Groups required by others are
+    /// not included if they are the excluded group.
+    pub fn setup_except_group(&self, group: &str) -> Result<Summary, Error> {
+        self.setup_selection(&[], &[group])
+    }
+
+    /// Configure a group, or a single value of a group when `target`
+    /// is in the form `group/value`.
+    pub fn setup_group(&self, target: &str) -> Result<Summary, Error> {
+        self.setup_selection(&[target], &[])
+    }
+
+    /// Configure the values matched by any of the `only` targets, or all values
+    /// when it is empty, and the values required by them, except the ones matched
+    /// by any of the `except` targets, even when required by others. Targets are
+    /// a group or a `group/value`, where the group can be a glob pattern like
+    /// `dev-*`, and each one must match at least one group. The tags set with
+    /// `with_tags` and `with_skip_tags` are applied to the selected values.
+    pub fn setup_selection<S>(&self, only: &[S], except: &[S]) -> Result<Summary, Error>
+    where
+        S: AsRef<str>,
+    {
+        self.setup_values(self.selection(only, except)?)
+    }
+
+    pub fn setup(&self) -> Result<Summary, Error> {
+        self.setup_selection::<&str>(&[], &[])
+    }
+
+    /// Preview the values selected like `setup_selection` without changing
+    /// anything, returning the unified diff of each link destination that
+    /// conflicts with a file, see `diff::diff`.
+    pub fn diff_selection<S>(&self, only: &[S], except: &[S]) -> Result<Vec<String>, Error>
+    where
+        S: AsRef<str>,
+    {
+        let mut diffs = vec![];
+        for selected in sort(self.selection(only, except)?)? {
+            for link in selected
+                .values
+                .iter()
+                .filter_map(|value| value.link.as_ref())
+            {
+                diffs.extend(diff::diff(link)?);
+            }
+        }
+        Ok(diffs)
+    }
+
+    fn selection<S>(&self, only: &[S], except: &[S]) -> Result<Vec<Selected<'_>>, Error>
+    where
+        S: AsRef<str>,
+    {
+        let resolve = |targets: &[S]| -> Result<Vec<(usize, usize)>, Error> {
+            let mut values = vec![];
+            for target in targets.iter() {
+                values.extend(self.resolve_pattern(target.as_ref())?);
+            }
+            Ok(values)
+        };
+        let only = resolve(only)?;
+        let mut except = resolve(except)?;
+
+        let groups = &self.config.groups;
+        let tagged =
+            |g: usize, v: usize, tags: &[String]| groups[g].values[v].tagged(&groups[g], tags);
+        for (g, group) in groups.iter().enumerate() {
+            except.extend(
+                (0..group.values.len())
+                    .filter(|&v| tagged(g, v, &self.skip_tags))
+                    .map(|v| (g, v)),
+            );
+        }
+
+        match only.is_empty() && self.tags.is_empty() {
+            true => self.select(|_, _| true, &except, false),
+            false => self.select(
+                |g, v| {
+                    (only.is_empty() || only.contains(&(g, v)))
+                        && (self.tags.is_empty() || tagged(g, v, &self.tags))
+                },
+                &except,
+                self.requires,
+            ),
+        }
     }
 
-    pub fn setup_except_group(&self, group: &str) -> Result<(), Error> {
-        for (g, values) in self.config.values.iter() {
-            if g != group {
-                info!("Configuring values of group {}", group);
-                self.process(values)?;
+    fn setup_values(&self, selection: Vec<Selected>) -> Result<Summary, Error> {
+        let start = Instant::now();
+        let selection = sort(selection)?;
+        let mut run = Run::default();
+        let _lock = match self.state_dir {
+            Some(ref dir) => {
+                let lock = Lock::acquire(dir)?;
+                run.state = Some(State::load(&dir.join(state::STATE_FILE))?);
+                Some(lock)
             }
+            None => None,
+        };
+        let hooks = &self.config.hooks;
+        self.hooks(&mut run, config::HOOKS, "before", &hooks.before)?;
+
+        for selected in selection {
+            let group = selected.group;
+            let label = format!("{}/{}", group.name, config::HOOKS);
+            info!("Configuring values of group {}", group.name);
+            let group_start = Instant::now();
+            run.summary
+                .groups
+                .push((group.name.clone(), Counts::default(), Duration::default()));
+            run.in_group = true;
+            self.hooks(&mut run, &label, "before", &group.hooks.before)?;
+            let changed = self.process(&mut run, group, &selected.values)?;
+            self.after_hooks(&mut run, &label, &group.hooks, changed)?;
+            run.in_group = false;
+            if let Some((_, _, elapsed)) = run.summary.groups.last_mut() {
+                *elapsed = group_start.elapsed();
+            }
+        }
+
+        self.handlers(&mut run)?;
+        let changed = run.changed;
+        self.after_hooks(&mut run, config::HOOKS, hooks, changed)?;
+
+        run.summary.elapsed = start.elapsed();
+        if !run.summary.failures.is_empty() {
+            return Err(Error::Failures(run.summary));
+        }
+        Ok(run.summary)
+    }
+
+    // Record the error of target and continue when keeping going, otherwise
+    // return it to stop the run.
+    fn fail(&self, run: &mut Run, target: String, err: Error) -> Result<(), Error> {
+        if !self.keep_going {
+            return Err(Error::Value(target, Box::new(err)));
         }
+        error!("{}: {}", target, err);
+        run.count(|counts| counts.failed += 1);
+        run.summary.failures.push(Failure { target, error: err });
         Ok(())
     }
 
-    pub fn setup_group(&self, group: &str) -> Result<(), Error> {
-        if let Some(values) = self.config.values.get(group) {
-            info!("Configuring values of group {}", group);
-            return self.process(&values);
+    // Execute the notified handlers once each, in the order that they are declared.
+    fn handlers(&self, run: &mut Run) -> Result<(), Error> {
+        if let Some(name) = run
+            .notified
+            .iter()
+            .find(|&&name| self.config.handler(name).is_none())
+        {
+            return Err(Error::InvalidHandler(name.to_string()));
+        }
+        for handler in self.config.handlers.iter() {
+            if !run.notified.contains(&handler.name.as_str()) {
+                continue;
+            }
+            let id = format!("{}/{}", config::HANDLERS, handler.name);
+            info!("Running handler {}", handler.name);
+            let record = Record::new(
+                config::HANDLERS,
+                &handler.name,
+                Kind::Shell,
+                &handler.shell.command_line(),
+            );
+            if let Err(err) = self.shell(run, &id, &handler.shell, record) {
+                self.fail(run, id, err)?;
+            }
         }
-        Err(Error::InvalidGroup)
+        Ok(())
     }
 
-    pub fn setup(&self) -> Result<(), Error> {
-        for (group, values) in self.config.values.iter() {
-            info!("Configuring values of group {}", group);
-            self.process(values)?;
+    fn after_hooks(
+        &self,
+        run: &mut Run,
+        label: &str,
+        hooks: &Hooks,
+        changed: bool,
+    ) -> Result<(), Error> {
+        if hooks.only_changed && !changed {
+            if !hooks.after.is_empty() {
+                info!("Skipping {}/after: nothing changed", label);
+            }
+            return Ok(());
+        }
+        self.hooks(run, label, "after", &hooks.after)
+    }
+
+    fn hooks(&self, run: &mut Run, label: &str, when: &str, shells: &[Shell]) -> Result<(), Error> {
+        let id = format!("{}/{}", label, when);
+        for shell in shells.iter() {
+            info!("Running {}: {}", id, shell.command_line());
+            let record = Record::new(label, when, Kind::Shell, &shell.command_line());
+            if let Err(err) = self.shell(run, &id, shell, record) {
+                self.fail(run, id.clone(), err)?;
+            }
         }
         Ok(())
     }
 
-    fn process(&self, values: &[Values]) -> Result<(), Error> {
+    // Select the values to configure given the position of the group and of
+    // the value in config, optionally including the values required by them.
+    // Excluded values are never selected.
+    fn select<F>(
+        &self,
+        selected: F,
+        excluded: &[(usize, usize)],
+        requires: bool,
+    ) -> Result<Vec<Selected<'_>>, Error>
+    where
+        F: Fn(usize, usize) -> bool,
+    {
+        let groups = &self.config.groups;
+        let mut marked: Vec<Vec<bool>> = groups
+            .iter()
+            .enumerate()
+            .map(|(g, group)| {
+                (0..group.values.len())
+                    .map(|v| selected(g, v) && !excluded.contains(&(g, v)))
+                    .collect()
+            })
+            .collect();
+
+        let mut changed = requires;
+        while changed {
+            changed = false;
+            for (g, group) in groups.iter().enumerate() {
+                for (v, value) in group.values.iter().enumerate() {
+                    if !marked[g][v] {
+                        continue;
+                    }
+                    for target in group.requires.iter().chain(value.requires.iter()) {
+                        for (rg, rv) in self.resolve(target)? {
+                            if !marked[rg][rv] && !excluded.contains(&(rg, rv)) {
+                                marked[rg][rv] = true;
+                                changed = true;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(groups
+            .iter()
+            .zip(marked)
+            .map(|(group, marked)| Selected {
+                group,
+                values: group
+                    .values
+                    .iter()
+                    .zip(marked)
+                    .filter(|(_, marked)| *marked)
+                    .map(|(value, _)| value)
+                    .collect(),
+            })
+            .filter(|selected| !selected.values.is_empty())
+            .collect())
+    }
+
+    // Find the position of values referenced by `group` or `group/value`.
+    fn resolve(&self, target: &str) -> Result<Vec<(usize, usize)>, Error> {
+        let (name, id) = config::split_target(target);
+        let g = self
+            .config
+            .groups
+            .iter()
+            .position(|group| group.name == name)
+            .ok_or_else(|| Error::InvalidGroup(name.to_string()))?;
+
+        let values: Vec<(usize, usize)> = self.config.groups[g]
+            .values
+            .iter()
+            .enumerate()
+            .filter(|(_, value)| id.is_none() || id == Some(value.id().as_str()))
+            .map(|(v, _)| (g, v))
+            .collect();
+
+        if id.is_some() && values.is_empty() {
+            return Err(Error::InvalidValue(target.to_string()));
+        }
+        Ok(values)
+    }
+
+    // Like `resolve`, but the group of target can be a glob pattern that
+    // must match at least one group.
+    fn resolve_pattern(&self, target: &str) -> Result<Vec<(usize, usize)>, Error> {
+        let (name, id) = config::split_target(target);
+        if !name.contains(&['*', '?', '['][..]) {
+            return self.resolve(target);
+        }
+        let pattern =
+            glob::Pattern::new(name).map_err(|_| Error::InvalidPattern(name.to_string()))?;
+        let mut values = vec![];
+        let mut matched = false;
+        for group in self.config.groups.iter() {
+            if !pattern.matches(&group.name) {
+                continue;
+            }
+            matched = true;
+            let target = match id {
+                Some(id) => format!("{}/{}", group.name, id),
+                None => group.name.clone(),
+            };
+            match self.resolve(&target) {
+                Ok(found) => values.extend(found),
+                // The value only needs to exist in some of the matched groups.
+                Err(Error::InvalidValue(_)) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        if !matched {
+            return Err(Error::InvalidGroup(name.to_string()));
+        }
+        if id.is_some() && values.is_empty() {
+            return Err(Error::InvalidValue(target.to_string()));
+        }
+        Ok(values)
+    }
+
+    // Configure the values of group, returning if any of them changed and
+    // adding the handlers notified by the changed values.
+    fn process<'c>(
+        &self,
+        run: &mut Run<'c>,
+        group: &Group,
+        values: &[&'c Values],
+    ) -> Result<bool, Error> {
+        let mut changed = false;
         for value in values.iter() {
-            self.process_value(value)?;
+            let id = format!("{}/{}", group.name, value.id());
+            if let Some(failed) = run.failed_requirement(group, value) {
+                let err = Error::Dependency(failed.to_string());
+                self.fail(run, id, err)?;
+                continue;
+            }
+            info!("Configuring {}", id);
+            match self.process_value(run, group, &id, value) {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(err) => {
+                    self.fail(run, id, err)?;
+                    continue;
+                }
+            }
+            changed = true;
+            run.changed = true;
+            for name in value.notify.iter() {
+                if !run.notified.contains(&name.as_str()) {
+                    run.notified.push(name);
+                }
+            }
         }
-        Ok(())
+        Ok(changed)
     }
 
-    fn process_value(&self, value: &Values) -> Result<(), Error> {
-        if let Some(ref package) = value.package {
-            self.pacman.install(&package)?;
+    // Apply the value, recording each successful action in the state.
+    fn process_value(
+        &self,
+        run: &mut Run,
+        group: &Group,
+        id: &str,
+        value: &Values,
+    ) -> Result<bool, Error> {
+        let record = |kind, target: &str| Record::new(&group.name, &value.id(), kind, target);
+        let mut changed = false;
+        for package in value.packages.iter() {
+            if self.pacman.install(package)? {
+                run.count(|counts| counts.installed += 1);
+                changed = true;
+                run.record(record(Kind::Package, &package.name))?;
+            } else {
+                run.count(|counts| counts.present += 1);
+            }
         }
 
         if let Some(ref link) = value.link {
-            symlink::symlink(link)?;
+            let linked = symlink::symlink(link)?;
+            run.count(|counts| {
+                counts.linked += linked.created;
+                counts.relinked += linked.relinked;
+                counts.present += linked.unchanged;
+            });
+            changed |= linked.changed();
+
+            // Files kept in place of the link were not applied by ipa.
+            if linked.conflicts == 0 {
+                let (src, dst) = link.paths()?;
+                let mut record = record(Kind::Link, &link.dst);
+                if src.is_file() {
+                    record.hash = Some(state::digest(&fs::read(&src).map_err(symlink::Error::Io)?));
+                }
+                record.paths = vec![src, dst];
+                run.record(record)?;
+            }
         }
 
         if let Some(ref shell) = value.shell {
-            shell::execute(&shell)?;
+            let record = record(Kind::Shell, &shell.command_line());
+            if self.shell(run, id, shell, record)?.is_some() {
+                changed = true;
+            }
         }
-        Ok(())
+        Ok(changed)
+    }
+
+    // Execute the shell command unless its guards skip it, returning its
+    // output if it was executed. Commands that fail are counted as executed
+    // too, but only successful executions are recorded in the state with the
+    // hash of their script, used by `on_change`.
+    fn shell(
+        &self,
+        run: &mut Run,
+        id: &str,
+        shell: &Shell,
+        mut record: Record,
+    ) -> Result<Option<shell::Output>, Error> {
+        let recorded = run.previous(&record).and_then(|previous| previous.hash);
+        let ctx = shell::Context {
+            label: id,
+            dir: self.config.dir.as_deref(),
+            recorded: recorded.as_deref(),
+            escalation: Some(&self.escalation),
+        };
+        if let Some(reason) = shell::skip_reason(shell, &ctx)? {
+            info!("Skipping command of {}: {}", id, reason);
+            run.count(|counts| counts.skipped += 1);
+            return Ok(None);
+        }
+        record.hash = shell::script_hash(shell, &ctx)?;
+        let result = shell::execute(shell, &ctx);
+        if matches!(
+            result,
+            Ok(_) | Err(shell::Error::Failed { .. }) | Err(shell::Error::Timeout { .. })
+        ) {
+            run.count(|counts| counts.executed += 1);
+        }
+        let output = result?;
+        if output.status.success() {
+            run.record(record)?;
+        }
+        Ok(Some(output))
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::cell::RefCell;
-    use tempfile::{tempdir, NamedTempFile};
+/// Value, hook or handler that failed when keeping going.
+#[derive(Debug)]
+pub struct Failure {
+    /// Id of value, like `group/value`, or of hook or handler.
+    pub target: String,
+    pub error: Error,
+}
+
+/// Number of actions of a run by their outcome.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Counts {
+    /// Packages installed.
+    pub installed: usize,
+
+    /// Packages already installed and links already created.
+    pub present: usize,
+
+    /// Links created.
+    pub linked: usize,
+
+    /// Existing files replaced by links.
+    pub relinked: usize,
+
+    /// Shell commands, hooks and handlers executed, including the failed ones.
+    pub executed: usize,
+
+    /// Shell commands skipped by their guards.
+    pub skipped: usize,
+
+    /// Values, hooks and handlers that failed when keeping going.
+    pub failed: usize,
+}
+
+impl Counts {
+    fn row(&self, name: &str, width: usize, elapsed: Duration) -> String {
+        format!(
+            "{:width$}  {:>9}  {:>7}  {:>6}  {:>8}  {:>8}  {:>7}  {:>6}  {:>8}",
+            name,
+            self.installed,
+            self.present,
+            self.linked,
+            self.relinked,
+            self.executed,
+            self.skipped,
+            self.failed,
+            format!("{:.2?}", elapsed),
+            width = width
+        )
+    }
+}
+
+/// Outcome of a run, returned by the `setup` methods of `Ipa`.
+#[derive(Debug, Default)]
+pub struct Summary {
+    /// Counts and elapsed time of each group, in the order that they were
+    /// configured.
+    pub groups: Vec<(String, Counts, Duration)>,
+
+    /// Counts of the whole run, including its hooks and handlers.
+    pub total: Counts,
 
-    struct FakePacman {
-        installed_packages: RefCell<Vec<String>>,
+    /// Failures when keeping going on errors.
+    pub failures: Vec<Failure>,
+
+    /// Elapsed time of the whole run.
+    pub elapsed: Duration,
+}
+
+impl std::fmt::Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let width = self
+            .groups
+            .iter()
+            .map(|(name, _, _)| name.len())
+            .chain(std::iter::once("group".len()))
+            .max()
+            .unwrap_or_default();
+        writeln!(
+            f,
+            "{:width$}  installed  present  linked  relinked  executed  skipped  failed   elapsed",
+            "group",
+            width = width
+        )?;
+        for (name, counts, elapsed) in self.groups.iter() {
+            writeln!(f, "{}", counts.row(name, width, *elapsed))?;
+        }
+        write!(f, "{}", self.total.row("total", width, self.elapsed))
     }
+}
+
+/// State of a run of the selected values.
+#[derive(Default)]
+struct Run<'c> {
+    /// If any value changed.
+    changed: bool,
+
+    /// Handlers notified by the values that changed.
+    notified: Vec<&'c str>,
+
+    /// If the actions are counted in the last group of summary.
+    in_group: bool,
+
+    summary: Summary,
+
+    /// State of previous runs, updated after each action.
+    state: Option<State>,
+}
 
-    impl FakePacman {
-        fn new() -> Self {
-            FakePacman {
-                installed_packages: RefCell::new(vec![]),
+impl<'c> Run<'c> {
+    // Previous record of the same action of record, if state is kept.
+    fn previous(&self, record: &Record) -> Option<Record> {
+        self.state.as_ref()?.previous(record).cloned()
+    }
+
+    // Add the record of a successful action to the state, saving it.
+    fn record(&mut self, record: Record) -> Result<(), state::Error> {
+        if let Some(ref mut state) = self.state {
+            state.record(record);
+            state.save()?;
+        }
+        Ok(())
+    }
+
+    // Count an action in the total and in the group being configured.
+    fn count<F>(&mut self, count: F)
+    where
+        F: Fn(&mut Counts),
+    {
+        count(&mut self.summary.total);
+        if self.in_group {
+            if let Some((_, counts, _)) = self.summary.groups.last_mut() {
+                count(counts);
             }
         }
     }
 
-    impl pacman::PackageManagement for FakePacman {
-        fn install(&self, package: &pacman::Package) -> Result<(), pacman::Error> {
-            self.installed_packages
-                .borrow_mut()
-                .push(package.name.clone());
-            Ok(())
+    // Find a failure of the groups or values required by value, or by its group.
+    fn failed_requirement(&self, group: &Group, value: &Values) -> Option<&str> {
+        group
+            .requires
+            .iter()
+            .chain(value.requires.iter())
+            .find_map(|target| {
+                self.summary
+                    .failures
+                    .iter()
+                    .map(|failure| failure.target.as_str())
+                    .find(|failed| {
+                        failed == target
+                            || (failed.starts_with(target.as_str())
+                                && failed[target.len()..].starts_with('/'))
+                    })
+            })
+    }
+}
+
+/// Values of a group selected to be configured.
+struct Selected<'c> {
+    group: &'c Group,
+    values: Vec<&'c Values>,
+}
+
+impl<'c> Selected<'c> {
+    /// Targets required by the group or by any of the selected values.
+    fn requires(&self) -> impl Iterator<Item = &str> {
+        self.group
+            .requires
+            .iter()
+            .chain(self.values.iter().flat_map(|value| value.requires.iter()))
+            .map(String::as_str)
+    }
+}
+
+// Sort the groups so that each one is configured after the groups that it
+// requires or declares in its `after`, breaking ties by `order` and then by
+// the position in config file. Values of the same group are sorted in the
+// same way, by the values that they require and their position.
+fn sort(mut selection: Vec<Selected>) -> Result<Vec<Selected>, Error> {
+    let preds: Vec<Vec<usize>> = selection
+        .iter()
+        .map(|selected| {
+            selected
+                .group
+                .after
+                .iter()
+                .map(String::as_str)
+                .chain(
+                    selected
+                        .requires()
+                        .map(|target| config::split_target(target).0),
+                )
+                .filter(|name| *name != selected.group.name)
+                .filter_map(|name| selection.iter().position(|s| s.group.name == name))
+                .collect()
+        })
+        .collect();
+
+    let order = toposort(&preds, |i| (selection[i].group.order, i)).map_err(|cycle| {
+        Error::Cycle(
+            cycle
+                .into_iter()
+                .map(|i| selection[i].group.name.clone())
+                .collect(),
+        )
+    })?;
+
+    for selected in selection.iter_mut() {
+        let name = &selected.group.name;
+        let ids: Vec<String> = selected.values.iter().map(|value| value.id()).collect();
+        let preds: Vec<Vec<usize>> = selected
+            .values
+            .iter()
+            .map(|value| {
+                value
+                    .requires
+                    .iter()
+                    .map(|target| config::split_target(target))
+                    .filter(|(group, _)| group == name)
+                    .filter_map(|(_, id)| ids.iter().position(|i| Some(i.as_str()) == id))
+                    .collect()
+            })
+            .collect();
+        let order = toposort(&preds, |i| i).map_err(|cycle| {
+            Error::Cycle(
+                cycle
+                    .into_iter()
+                    .map(|i| format!("{}/{}", name, ids[i]))
+                    .collect(),
+            )
+        })?;
+        let values = order.into_iter().map(|i| selected.values[i]).collect();
+        selected.values = values;
+    }
+
+    let mut selection: Vec<Option<Selected>> = selection.into_iter().map(Some).collect();
+    Ok(order
+        .into_iter()
+        .filter_map(|i| selection[i].take())
+        .collect())
+}
+
+/// Sort nodes topologically given the predecessors of each node, picking
+/// the ready node with the lowest priority first. Return the nodes of
+/// a cycle if there is one.
+fn toposort<F, K>(preds: &[Vec<usize>], priority: F) -> Result<Vec<usize>, Vec<usize>>
+where
+    F: Fn(usize) -> K,
+    K: Ord,
+{
+    let mut done = vec![false; preds.len()];
+    let mut sorted = Vec::with_capacity(preds.len());
+    while sorted.len() < preds.len() {
+        let next = (0..preds.len())
+            .filter(|&i| !done[i] && preds[i].iter().all(|&p| done[p]))
+            .min_by_key(|&i| priority(i));
+        match next {
+            Some(i) => {
+                done[i] = true;
+                sorted.push(i);
+            }
+            None => return Err(find_cycle(preds, &done)),
         }
     }
+    Ok(sorted)
+}
+
+// Every pending node has at least one pending predecessor, so walking
+// through them will eventually visit the same node twice.
+fn find_cycle(preds: &[Vec<usize>], done: &[bool]) -> Vec<usize> {
+    let mut path = vec![];
+    let mut node = (0..preds.len()).find(|&i| !done[i]).unwrap_or_default();
+    while !path.contains(&node) {
+        path.push(node);
+        node = match preds[node].iter().find(|&&p| !done[p]) {
+            Some(&pred) => pred,
+            None => break,
+        };
+    }
+    let start = path.iter().position(|&n| n == node).unwrap_or_default();
+    let mut cycle = path.split_off(start);
+    cycle.push(node);
+    cycle
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pacman::FakePacman;
+    use tempfile::{tempdir, NamedTempFile};
 
     #[test]
     fn test_setup_all_packages() {
@@ -152,7 +931,7 @@ gui:
         let config = Config::new(&content).unwrap();
 
         let pacman = FakePacman::new();
-        let ipa = Ipa::new(config, &pacman);
+        let ipa = Ipa::new(config, &pacman).without_state();
 
         ipa.setup().unwrap();
 
@@ -174,7 +953,7 @@ gui:
         let config = Config::new(&content).unwrap();
 
         let pacman = FakePacman::new();
-        let ipa = Ipa::new(config, &pacman);
+        let ipa = Ipa::new(config, &pacman).without_state();
 
         ipa.setup_group("dev").unwrap();
 
@@ -198,13 +977,622 @@ gui:
         let config = Config::new(&content).unwrap();
 
         let pacman = FakePacman::new();
-        let ipa = Ipa::new(config, &pacman);
+        let ipa = Ipa::new(config, &pacman).without_state();
 
         ipa.setup_except_group("dev").unwrap();
 
         assert_eq!(1, pacman.installed_packages.borrow().len());
     }
 
+    #[test]
+    fn test_setup_groups_in_file_order() {
+        let content = "
+base:
+  - package: git
+dev:
+  - package: neovim
+gui:
+  - package: alacritty
+tools:
+  - package: fzf
+            ";
+
+        let config = Config::new(content).unwrap();
+
+        let pacman = FakePacman::new();
+        let ipa = Ipa::new(config, &pacman).without_state();
+
+        ipa.setup_except_group("gui").unwrap();
+
+        assert_eq!(
+            vec!["git", "neovim", "fzf"],
+            *pacman.installed_packages.borrow()
+        );
+    }
+
+    #[test]
+    fn test_setup_groups_with_order_and_after() {
+        let content = "
+dev:
+  after: [base]
+  values:
+    - package: neovim
+gui:
+  order: -1
+  values:
+    - package: alacritty
+base:
+  - package: git
+            ";
+
+        let config = Config::new(content).unwrap();
+
+        let pacman = FakePacman::new();
+        let ipa = Ipa::new(config, &pacman).without_state();
+
+        ipa.setup().unwrap();
+
+        assert_eq!(
+            vec!["alacritty", "git", "neovim"],
+            *pacman.installed_packages.borrow()
+        );
+    }
+
+    #[test]
+    fn test_setup_groups_with_cycle() {
+        let content = "
+dev:
+  after: [base]
+  values:
+    - package: neovim
+base:
+  values:
+    - package: git
+      requires: [dev]
+            ";
+
+        let config = Config::new(content).unwrap();
+
+        let pacman = FakePacman::new();
+        let ipa = Ipa::new(config, &pacman).without_state();
+
+        let err = ipa.setup().unwrap_err();
+        assert_eq!("Cyclic dependency: dev -> base -> dev", err.to_string());
+        assert!(pacman.installed_packages.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_setup_group_with_requires() {
+        let content = "
+dev:
+  - package: tmux
+    requires: [base]
+gui:
+  - package: alacritty
+base:
+  requires: [tools]
+  values:
+    - package: git
+tools:
+  - package: curl
+            ";
+
+        let config = Config::new(content).unwrap();
+
+        let pacman = FakePacman::new();
+        let ipa = Ipa::new(config, &pacman).without_state();
+
+        ipa.setup_group("dev").unwrap();
+
+        assert_eq!(
+            vec!["curl", "git", "tmux"],
+            *pacman.installed_packages.borrow()
+        );
+    }
+
+    #[test]
+    fn test_setup_group_without_requires() {
+        let content = "
+dev:
+  requires: [base]
+  values:
+    - package: tmux
+base:
+  - package: git
+            ";
+
+        let config = Config::new(content).unwrap();
+
+        let pacman = FakePacman::new();
+        let ipa = Ipa::new(config, &pacman)
+            .without_state()
+            .with_requires(false);
+
+        ipa.setup_group("dev").unwrap();
+
+        assert_eq!(vec!["tmux"], *pacman.installed_packages.borrow());
+    }
+
+    #[test]
+    fn test_setup_single_value() {
+        let content = "
+dev:
+  - package: neovim
+  - name: terminal
+    package: tmux
+    requires: [base/git, dev/fzf]
+  - package: fzf
+base:
+  - package: git
+  - package: curl
+            ";
+
+        let config = Config::new(content).unwrap();
+
+        let pacman = FakePacman::new();
+        let ipa = Ipa::new(config, &pacman).without_state();
+
+        ipa.setup_group("dev/terminal").unwrap();
+
+        assert_eq!(
+            vec!["git", "fzf", "tmux"],
+            *pacman.installed_packages.borrow()
+        );
+    }
+
+    #[test]
+    fn test_setup_invalid_value() {
+        let content = "
+dev:
+  - package: neovim
+            ";
+
+        let config = Config::new(content).unwrap();
+
+        let pacman = FakePacman::new();
+        let ipa = Ipa::new(config, &pacman).without_state();
+
+        let err = ipa.setup_group("dev/tmux").unwrap_err();
+        assert_eq!("Invalid value name dev/tmux", err.to_string());
+    }
+
+    #[test]
+    fn test_setup_selection() {
+        let content = "
+base:
+  - package: git
+dev-rust:
+  - package: rustup
+    requires: [base]
+dev-go:
+  - package: go
+gui:
+  - package: alacritty
+            ";
+
+        let setup = |only: &[&str], except: &[&str]| {
+            let config = Config::new(content).unwrap();
+            let pacman = FakePacman::new();
+            let ipa = Ipa::new(config, &pacman).without_state();
+            ipa.setup_selection(only, except)
+                .map(|_| pacman.installed_packages.borrow().clone())
+        };
+
+        assert_eq!(
+            vec!["git", "rustup", "go", "alacritty"],
+            setup(&["dev-*", "gui"], &[]).unwrap()
+        );
+        assert_eq!(
+            vec!["rustup"],
+            setup(&["dev-*"], &["base", "dev-go"]).unwrap()
+        );
+        assert_eq!(vec!["git", "alacritty"], setup(&[], &["dev-*"]).unwrap());
+        assert_eq!(vec!["go"], setup(&["*/go"], &[]).unwrap());
+
+        let err = setup(&[], &["dve"]).unwrap_err();
+        assert_eq!("Invalid group name dve", err.to_string());
+        let err = setup(&["web-*"], &[]).unwrap_err();
+        assert_eq!("Invalid group name web-*", err.to_string());
+        let err = setup(&["dev-*/tmux"], &[]).unwrap_err();
+        assert_eq!("Invalid value name dev-*/tmux", err.to_string());
+    }
+
+    #[test]
+    fn test_setup_tags() {
+        let content = "
+base:
+  - package: git
+dev:
+  tags: dev
+  values:
+    - package: rustup
+      tags: [rust]
+      requires: [base]
+    - package: go
+gui:
+  - package: alacritty
+    tags: [rust, gui]
+  - package: i3
+            ";
+
+        let setup = |only: &[&str], tags: &[&str], skip_tags: &[&str]| {
+            let tags: Vec<String> = tags.iter().map(|t| t.to_string()).collect();
+            let skip_tags: Vec<String> = skip_tags.iter().map(|t| t.to_string()).collect();
+            let config = Config::new(content).unwrap();
+            let pacman = FakePacman::new();
+            let ipa = Ipa::new(config, &pacman)
+                .without_state()
+                .with_tags(&tags)
+                .with_skip_tags(&skip_tags);
+            ipa.setup_selection(only, &[]).unwrap();
+            let installed = pacman.installed_packages.borrow().clone();
+            installed
+        };
+
+        assert_eq!(
+            vec!["git", "rustup", "alacritty"],
+            setup(&[], &["rust"], &[])
+        );
+        assert_eq!(vec!["git", "rustup"], setup(&["dev"], &["rust"], &[]));
+        assert_eq!(vec!["git", "rustup", "go"], setup(&[], &["dev"], &[]));
+        assert_eq!(vec!["git", "i3"], setup(&[], &[], &["rust", "dev"]));
+        assert_eq!(vec!["alacritty"], setup(&[], &["gui"], &["dev"]));
+    }
+
+    #[test]
+    fn test_skip_shell_with_guards() {
+        let dir = tempdir().unwrap();
+        let created = dir.path().join("created");
+        let skipped = dir.path().join("skipped");
+
+        let content = format!(
+            "
+dev:
+  - shell:
+      command: touch {created:?}
+      creates: {created:?}
+  - shell:
+      command: touch {skipped:?}
+      creates: {created:?}
+  - shell:
+      command: touch {skipped:?}
+      unless: test -e {created:?}
+",
+            created = created,
+            skipped = skipped,
+        );
+
+        let config = Config::new(&content).unwrap();
+
+        let pacman = FakePacman::new();
+        let ipa = Ipa::new(config, &pacman).without_state();
+
+        ipa.setup().unwrap();
+
+        assert!(created.exists());
+        assert!(!skipped.exists());
+    }
+
+    #[test]
+    fn test_hooks() {
+        let dir = tempdir().unwrap();
+        let log = dir.path().join("log");
+
+        let content = format!(
+            "
+hooks:
+  before: echo before >> {log:?}
+  after: echo after >> {log:?}
+base:
+  hooks:
+    after: echo base >> {log:?}
+    only_changed: true
+  values:
+    - package: git
+dev:
+  hooks:
+    before: echo dev >> {log:?}
+  values:
+    - package: neovim
+",
+            log = log,
+        );
+
+        let config = Config::new(&content).unwrap();
+        let pacman = FakePacman::new();
+        let ipa = Ipa::new(config, &pacman).without_state();
+
+        ipa.setup().unwrap();
+        assert_eq!(
+            "before\nbase\ndev\nafter\n",
+            std::fs::read_to_string(&log).unwrap()
+        );
+
+        // Packages are already installed, so after hooks of base are skipped.
+        std::fs::remove_file(&log).unwrap();
+        ipa.setup().unwrap();
+        assert_eq!(
+            "before\ndev\nafter\n",
+            std::fs::read_to_string(&log).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_handlers() {
+        let dir = tempdir().unwrap();
+        let log = dir.path().join("log");
+
+        let content = format!(
+            "
+handlers:
+  restart: echo restart >> {log:?}
+  reload: echo reload >> {log:?}
+  unused: echo unused >> {log:?}
+dev:
+  - package: neovim
+    notify: [reload, restart]
+  - package: tmux
+    notify: reload
+  - package: git
+    notify: unused
+",
+            log = log,
+        );
+
+        let config = Config::new(&content).unwrap();
+        let pacman = FakePacman::new();
+        pacman
+            .installed_packages
+            .borrow_mut()
+            .push(String::from("git"));
+        let ipa = Ipa::new(config, &pacman).without_state();
+
+        ipa.setup().unwrap();
+        assert_eq!("restart\nreload\n", std::fs::read_to_string(&log).unwrap());
+
+        // Nothing changed, so no handler is notified.
+        std::fs::remove_file(&log).unwrap();
+        ipa.setup().unwrap();
+        assert!(!log.exists());
+    }
+
+    #[test]
+    fn test_keep_going() {
+        let content = "
+base:
+  - name: broken
+    shell: exit 1
+  - package: git
+dev:
+  - package: neovim
+    requires: [base/broken]
+  - package: tmux
+gui:
+  requires: [dev]
+  values:
+    - package: i3
+";
+        let pacman = FakePacman::new();
+        let ipa = Ipa::new(Config::new(content).unwrap(), &pacman).without_state();
+        assert!(matches!(ipa.setup(), Err(Error::Value(..))));
+        assert!(pacman.installed_packages.borrow().is_empty());
+
+        let ipa = Ipa::new(Config::new(content).unwrap(), &pacman)
+            .without_state()
+            .with_keep_going(true);
+        let summary = match ipa.setup() {
+            Err(Error::Failures(summary)) => summary,
+            result => panic!("expected failures, got {:?}", result),
+        };
+        let targets: Vec<&str> = summary.failures.iter().map(|f| f.target.as_str()).collect();
+        assert_eq!(vec!["base/broken", "dev/neovim", "gui/i3"], targets);
+        assert_eq!(
+            vec!["git", "tmux"],
+            pacman.installed_packages.borrow().clone()
+        );
+        assert_eq!(3, summary.total.failed);
+        assert!(Error::Failures(summary)
+            .to_string()
+            .contains("\n  dev/neovim   Skipped, requires base/broken that failed"));
+    }
+
+    #[test]
+    fn test_summary() {
+        let dir = tempdir().unwrap();
+        let src = NamedTempFile::new().unwrap();
+        let content = format!(
+            "
+base:
+  - package: [git, fzf]
+  - link: {src} -> {dst}
+dev:
+  - package: git
+  - shell:
+      command: echo foo
+      unless: 'true'
+",
+            src = src.path().display(),
+            dst = dir.path().join("dst").display(),
+        );
+
+        let pacman = FakePacman::new();
+        let ipa = Ipa::new(Config::new(&content).unwrap(), &pacman).without_state();
+        let summary = ipa.setup().unwrap();
+
+        let base = Counts {
+            installed: 2,
+            linked: 1,
+            ..Counts::default()
+        };
+        let dev = Counts {
+            present: 1,
+            skipped: 1,
+            ..Counts::default()
+        };
+        let groups: Vec<(&str, Counts)> = summary
+            .groups
+            .iter()
+            .map(|(name, counts, _)| (name.as_str(), *counts))
+            .collect();
+        assert_eq!(vec![("base", base), ("dev", dev)], groups);
+        assert!(summary
+            .groups
+            .iter()
+            .all(|(_, _, elapsed)| *elapsed <= summary.elapsed));
+        assert_eq!(
+            Counts {
+                installed: 2,
+                present: 1,
+                linked: 1,
+                skipped: 1,
+                ..Counts::default()
+            },
+            summary.total
+        );
+
+        let summary = ipa.setup().unwrap();
+        assert_eq!(4, summary.total.present);
+        assert!(summary
+            .to_string()
+            .starts_with("group  installed  present  linked  relinked  executed  skipped  failed   elapsed\nbase           0        3       0"));
+        assert!(summary
+            .to_string()
+            .lines()
+            .last()
+            .unwrap()
+            .starts_with("total          0        4       0         0         0        1       0"));
+
+        // Failed commands are counted as executed too.
+        let ipa = Ipa::new(Config::new("base:\n  - shell: 'false'\n").unwrap(), &pacman)
+            .without_state()
+            .with_keep_going(true);
+        let summary = match ipa.setup() {
+            Err(Error::Failures(summary)) => summary,
+            result => panic!("expected failures, got {:?}", result),
+        };
+        assert_eq!(1, summary.total.executed);
+        assert_eq!(1, summary.total.failed);
+    }
+
+    #[test]
+    fn test_state() {
+        let dir = tempdir().unwrap();
+        let state_dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        std::fs::write(&src, "foo").unwrap();
+        let dst = dir.path().join("dst").display().to_string();
+        let conflict = dir.path().join("conflict");
+        std::fs::write(&conflict, "bar").unwrap();
+        let script = dir.path().join("script.sh");
+        std::fs::write(&script, "true\n").unwrap();
+
+        let content = format!(
+            "
+dev:
+  - name: editor
+    package: neovim
+    link: {src} -> {dst}
+  - shell: echo foo
+  - shell:
+      command: echo bar
+      unless: 'true'
+  - package: git
+  - link: {src} -> {conflict}
+  - shell:
+      script: {script}
+      on_change: true
+",
+            src = src.display(),
+            dst = dst,
+            conflict = conflict.display(),
+            script = script.display(),
+        );
+
+        let pacman = FakePacman::new();
+        pacman
+            .installed_packages
+            .borrow_mut()
+            .push(String::from("git"));
+        let ipa =
+            Ipa::new(Config::new(&content).unwrap(), &pacman).with_state_dir(state_dir.path());
+        ipa.setup().unwrap();
+
+        let state = State::load(&state_dir.path().join(state::STATE_FILE)).unwrap();
+        let records: Vec<(&str, &str, Kind, &str)> = state
+            .records
+            .iter()
+            .map(|r| (r.group.as_str(), r.id.as_str(), r.kind, r.target.as_str()))
+            .collect();
+        assert_eq!(
+            vec![
+                ("dev", "editor", Kind::Package, "neovim"),
+                ("dev", "editor", Kind::Link, dst.as_str()),
+                ("dev", "echo foo", Kind::Shell, "echo foo"),
+                (
+                    "dev",
+                    script.to_str().unwrap(),
+                    Kind::Shell,
+                    script.to_str().unwrap()
+                ),
+            ],
+            records
+        );
+        assert_eq!(vec![src, dir.path().join("dst")], state.records[1].paths);
+        assert_eq!(Some(state::digest(b"foo")), state.records[1].hash);
+        assert_eq!(Some(state::digest(b"true\n")), state.records[3].hash);
+
+        // The script is skipped by `on_change` until its content changes.
+        assert_eq!(2, ipa.setup().unwrap().total.skipped);
+        std::fs::write(&script, "true\ntrue\n").unwrap();
+        assert_eq!(1, ipa.setup().unwrap().total.skipped);
+
+        // Other run can not start while the state is locked.
+        let _lock = Lock::acquire(state_dir.path()).unwrap();
+        assert!(matches!(
+            ipa.setup(),
+            Err(Error::State(state::Error::Locked(_)))
+        ));
+    }
+
+    #[test]
+    fn test_diff_selection() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        let dst = dir.path().join("dst");
+        std::fs::write(&src, "foo\n").unwrap();
+        std::fs::write(&dst, "bar\n").unwrap();
+        let touched = dir.path().join("touched");
+
+        let content = format!(
+            "
+dev:
+  - package: neovim
+    link: {src} -> {dst}
+  - shell: touch {touched}
+gui:
+  - link: {src} -> {dst}
+",
+            src = src.display(),
+            dst = dst.display(),
+            touched = touched.display(),
+        );
+        let pacman = FakePacman::new();
+        let ipa = Ipa::new(Config::new(&content).unwrap(), &pacman).without_state();
+
+        let diffs = ipa.diff_selection(&["dev"], &[]).unwrap();
+        assert_eq!(
+            vec![format!(
+                "--- {}\n+++ {}\n@@ -1 +1 @@\n-bar\n+foo\n",
+                dst.display(),
+                src.display()
+            )],
+            diffs
+        );
+        assert!(pacman.installed_packages.borrow().is_empty());
+        assert!(!touched.exists());
+        assert_eq!("bar\n", std::fs::read_to_string(&dst).unwrap());
+    }
+
     #[test]
     fn test_link() {
         let src_dir = tempdir().unwrap();
@@ -226,7 +1614,7 @@ dev:
         let config = Config::new(&content).unwrap();
 
         let pacman = FakePacman::new();
-        let ipa = Ipa::new(config, &pacman);
+        let ipa = Ipa::new(config, &pacman).without_state();
 
         ipa.setup().unwrap();
 
@@ -1,12 +1,39 @@
-use log::debug;
+use crate::{privilege::Escalation, state};
+use log::{debug, info, warn};
 use serde::{Deserialize, Serialize};
-use std::io;
-use std::process::{Command, Output};
+use shellexpand::LookupError;
+use std::collections::{BTreeMap, VecDeque};
+use std::env::VarError;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Number of lines of output kept to report errors.
+const TAIL_LINES: usize = 20;
 
 #[derive(Debug)]
 pub enum Error {
     /// io error to execute command.
     Io(io::Error),
+
+    /// Could not expand path
+    ShellExpand(LookupError<VarError>),
+
+    /// Command did not finish before the timeout, in seconds.
+    Timeout { command: String, timeout: u64 },
+
+    /// Command exited with non zero status, code is None if it was killed by a signal.
+    Failed {
+        command: String,
+        code: Option<i32>,
+        stderr: String,
+    },
 }
 
 impl From<io::Error> for Error {
@@ -15,34 +42,472 @@ impl From<io::Error> for Error {
     }
 }
 
+impl From<LookupError<VarError>> for Error {
+    fn from(val: LookupError<VarError>) -> Self {
+        Error::ShellExpand(val)
+    }
+}
+
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             Error::Io(e) => write!(f, "{}", e),
+            Error::ShellExpand(e) => write!(f, "Unable to expand path: {}", e),
+            Error::Timeout { command, timeout } => write!(
+                f,
+                "Command {:?} timed out after {} seconds",
+                command, timeout
+            ),
+            Error::Failed {
+                command,
+                code,
+                stderr,
+            } => {
+                match code {
+                    Some(code) => {
+                        write!(f, "Command {:?} failed with exit code {}", command, code)?
+                    }
+                    None => write!(f, "Command {:?} was terminated by a signal", command)?,
+                }
+                if !stderr.is_empty() {
+                    write!(f, ": {}", stderr)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Program used to execute the commands.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Interpreter {
+    #[default]
+    Bash,
+    Sh,
+    Zsh,
+    Fish,
+    Python,
+}
+
+impl Interpreter {
+    fn program(self) -> &'static str {
+        match self {
+            Interpreter::Bash => "bash",
+            Interpreter::Sh => "sh",
+            Interpreter::Zsh => "zsh",
+            Interpreter::Fish => "fish",
+            Interpreter::Python => "python3",
         }
     }
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
 pub struct Shell {
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub command: String,
+
+    /// Script executed instead of command, relative to the directory of config file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script: Option<String>,
+
+    /// Arguments of script.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+
+    /// Only execute the script again when its content changes.
+    #[serde(default)]
+    pub on_change: bool,
+
+    /// Interpreter of command, or of script instead of its shebang. Default to bash.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interpreter: Option<Interpreter>,
+
+    /// Run the command as this user, like `root`, using the escalation command.
+    #[serde(default, rename = "become", skip_serializing_if = "Option::is_none")]
+    pub become_user: Option<String>,
+
+    /// Working directory of command, relative to the directory of config file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+
+    /// Environment variables added to the command.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+
+    /// Maximum time in seconds that the command can run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+
+    #[serde(default)]
+    pub ignore_errors: bool,
+
+    /// Do not show the output of command.
+    #[serde(default)]
+    pub quiet: bool,
+
+    /// Skip the command if this path exists.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub creates: Option<String>,
+
+    /// Skip the command if this other command succeeds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unless: Option<String>,
+
+    /// Skip the command if this other command fails.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub onlyif: Option<String>,
 }
 
 impl Shell {
     pub fn new(command: &str) -> Self {
         Shell {
             command: command.to_string(),
+            script: None,
+            args: vec![],
+            on_change: false,
+            interpreter: None,
+            become_user: None,
+            cwd: None,
+            env: BTreeMap::new(),
+            timeout: None,
+            ignore_errors: false,
+            quiet: false,
+            creates: None,
+            unless: None,
+            onlyif: None,
+        }
+    }
+
+    pub fn script(script: &str, args: &[&str]) -> Self {
+        let mut shell = Shell::new("");
+        shell.script = Some(script.to_string());
+        shell.args = args.iter().map(|arg| arg.to_string()).collect();
+        shell
+    }
+
+    /// The command, or the script with its arguments.
+    pub fn command_line(&self) -> String {
+        match self.script {
+            Some(ref script) => std::iter::once(script)
+                .chain(self.args.iter())
+                .map(String::as_str)
+                .collect::<Vec<&str>>()
+                .join(" "),
+            None => self.command.clone(),
+        }
+    }
+}
+
+impl FromStr for Shell {
+    type Err = String;
+
+    /// Parse the shorthand form `shell: "nvim +PlugInstall +qall"`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim().is_empty() {
+            return Err(String::from("shell command can not be empty"));
+        }
+        Ok(Shell::new(s))
+    }
+}
+
+/// Where shell commands are executed.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Context<'a> {
+    /// Prefix of each line of output.
+    pub label: &'a str,
+
+    /// Directory of config file, used to resolve relative paths and as
+    /// the default working directory.
+    pub dir: Option<&'a Path>,
+
+    /// Hash of script recorded by its last successful execution, used by `on_change`.
+    pub recorded: Option<&'a str>,
+
+    /// Command used to run as other user, `sudo` if not set.
+    pub escalation: Option<&'a Escalation>,
+}
+
+/// Evaluate the `creates`, `unless`, `onlyif` and `on_change` guards of shell,
+/// returning the reason to skip the command, if any.
+pub fn skip_reason(shell: &Shell, ctx: &Context) -> Result<Option<String>, Error> {
+    if let Some(path) = creates(shell, ctx)? {
+        if path.exists() {
+            return Ok(Some(format!("{} already exists", path.display())));
+        }
+    }
+    if let Some(ref unless) = shell.unless {
+        if succeeds(shell, unless, ctx)? {
+            return Ok(Some(format!("{:?} succeeded", unless)));
+        }
+    }
+    if let Some(ref onlyif) = shell.onlyif {
+        if !succeeds(shell, onlyif, ctx)? {
+            return Ok(Some(format!("{:?} failed", onlyif)));
+        }
+    }
+    if shell.on_change
+        && ctx.recorded.is_some()
+        && ctx.recorded == script_hash(shell, ctx)?.as_deref()
+    {
+        return Ok(Some(String::from("script did not change")));
+    }
+    Ok(None)
+}
+
+/// Expanded path of the `creates` guard of shell, relative to its working directory.
+pub fn creates(shell: &Shell, ctx: &Context) -> Result<Option<PathBuf>, Error> {
+    match shell.creates {
+        Some(ref creates) => Ok(Some(resolve(creates, working_dir(shell, ctx)?.as_deref())?)),
+        None => Ok(None),
+    }
+}
+
+/// Hash of the current content of the script of shell, if it runs a script.
+pub fn script_hash(shell: &Shell, ctx: &Context) -> Result<Option<String>, Error> {
+    match shell.script {
+        Some(ref script) => Ok(Some(state::digest(&fs::read(resolve(script, ctx.dir)?)?))),
+        None => Ok(None),
+    }
+}
+
+// Check if a command exits successfully, discarding its output.
+fn succeeds(shell: &Shell, command: &str, ctx: &Context) -> Result<bool, Error> {
+    debug!("Checking command: {}", command);
+    Ok(prepare_command(shell, command, ctx)?
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()?
+        .success())
+}
+
+// Create the process to execute the command or the script of shell.
+fn prepare(shell: &Shell, ctx: &Context) -> Result<Command, Error> {
+    let script = match shell.script {
+        Some(ref script) => resolve(script, ctx.dir)?,
+        None => return prepare_command(shell, &shell.command, ctx),
+    };
+
+    let mut argv: Vec<OsString> = match (shell.interpreter, shebang(&script)?) {
+        (Some(interpreter), _) => vec![interpreter.program().into()],
+        (None, Some(shebang)) => shebang.into_iter().map(OsString::from).collect(),
+        (None, None) => vec![Interpreter::default().program().into()],
+    };
+    argv.push(script.into());
+    argv.extend(shell.args.iter().map(OsString::from));
+    environment(shell, &argv, ctx)
+}
+
+// Create the process to execute command with the interpreter of shell.
+fn prepare_command(shell: &Shell, command: &str, ctx: &Context) -> Result<Command, Error> {
+    let interpreter = shell.interpreter.unwrap_or_default();
+    environment(shell, &[interpreter.program(), "-c", command], ctx)
+}
+
+// Create the process to execute program with args, in the working directory
+// and with the environment and user of shell.
+fn environment<S>(shell: &Shell, argv: &[S], ctx: &Context) -> Result<Command, Error>
+where
+    S: AsRef<OsStr>,
+{
+    let mut process = match shell.become_user {
+        Some(ref user) => ctx
+            .escalation
+            .cloned()
+            .unwrap_or_default()
+            .command(user, &shell.env, argv),
+        None => {
+            let mut process = Command::new(&argv[0]);
+            process.args(&argv[1..]).envs(shell.env.iter());
+            process
+        }
+    };
+    if let Some(cwd) = working_dir(shell, ctx)? {
+        process.current_dir(cwd);
+    }
+    Ok(process)
+}
+
+// Read the interpreter and its arguments from the first line of script, like
+// `#!/usr/bin/env bash`.
+fn shebang(script: &Path) -> Result<Option<Vec<String>>, Error> {
+    let mut line = String::new();
+    BufReader::new(fs::File::open(script)?).read_line(&mut line)?;
+    if !line.starts_with("#!") {
+        return Ok(None);
+    }
+    let parts: Vec<String> = line[2..].split_whitespace().map(String::from).collect();
+    if parts.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(parts))
+}
+
+fn working_dir(shell: &Shell, ctx: &Context) -> Result<Option<PathBuf>, Error> {
+    match shell.cwd {
+        Some(ref cwd) => Ok(Some(resolve(cwd, ctx.dir)?)),
+        None => Ok(ctx.dir.map(Path::to_path_buf)),
+    }
+}
+
+// Expand a path like `~/some/path`, joining it to dir if it is relative.
+fn resolve(path: &str, dir: Option<&Path>) -> Result<PathBuf, Error> {
+    let path = PathBuf::from(shellexpand::full(path)?.as_ref());
+    match dir {
+        Some(dir) if path.is_relative() => Ok(dir.join(path)),
+        _ => Ok(path),
+    }
+}
+
+/// Output of an executed command, keeping only the last lines of stdout and stderr.
+#[derive(Debug)]
+pub struct Output {
+    pub status: ExitStatus,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// Execute the shell command, logging each line of its output prefixed by
+/// the label of context while the command is running.
+pub fn execute(shell: &Shell, ctx: &Context) -> Result<Output, Error> {
+    let command = shell.command_line();
+    debug!("Executing command: {}", command);
+    let mut child = prepare(shell, ctx)?
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        // Run in a new process group, so the command and its children can be killed together.
+        .process_group(0)
+        .spawn()?;
+
+    let stdout = stream(child.stdout.take(), ctx.label, shell.quiet);
+    let stderr = stream(child.stderr.take(), ctx.label, shell.quiet);
+    let status = match shell.timeout {
+        Some(timeout) => {
+            // Commands run as other user must be killed through the escalation command.
+            let escalation = shell
+                .become_user
+                .as_ref()
+                .map(|_| ctx.escalation.cloned().unwrap_or_default());
+            wait_timeout(
+                &mut child,
+                Duration::from_secs(timeout),
+                escalation.as_ref(),
+            )?
+        }
+        None => Some(child.wait()?),
+    };
+    let stdout = stdout.join().unwrap_or_default();
+    let stderr = stderr.join().unwrap_or_default();
+
+    let output = match status {
+        Some(status) => Output {
+            status,
+            stdout,
+            stderr,
+        },
+        None => {
+            return Err(Error::Timeout {
+                command,
+                timeout: shell.timeout.unwrap_or_default(),
+            })
+        }
+    };
+
+    if output.status.success() {
+        return Ok(output);
+    }
+
+    let err = Error::Failed {
+        command,
+        code: output.status.code(),
+        stderr: output.stderr.trim().to_string(),
+    };
+    if shell.ignore_errors {
+        warn!("Ignoring error: {}", err);
+        return Ok(output);
+    }
+    Err(err)
+}
+
+// Wait for the child until the timeout, killing its process group if it is
+// still running. Return None if the child was killed.
+fn wait_timeout(
+    child: &mut Child,
+    timeout: Duration,
+    escalation: Option<&Escalation>,
+) -> Result<Option<ExitStatus>, Error> {
+    let start = Instant::now();
+    while start.elapsed() < timeout {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    if let Err(err) = kill_group(child, escalation) {
+        // Kill at least the direct child, so it can be waited.
+        if child.kill().is_err() {
+            return Err(err);
+        }
+        warn!("Could not kill the children of command: {}", err);
+    }
+    child.wait()?;
+    Ok(None)
+}
+
+// Kill the process group of child. The escalation command is used to kill
+// it as root, since the group of a command run as other user, like `sudo`
+// itself, can not be killed by ipa.
+fn kill_group(child: &Child, escalation: Option<&Escalation>) -> Result<(), Error> {
+    let pgid = child.id() as libc::pid_t;
+    if let Some(escalation) = escalation {
+        let argv = ["kill", "-KILL", "--", &format!("-{}", pgid)];
+        let output = escalation
+            .command("root", &BTreeMap::new(), &argv)
+            .stdout(Stdio::null())
+            .output()?;
+        if !output.status.success() {
+            return Err(Error::Failed {
+                command: argv.join(" "),
+                code: output.status.code(),
+                stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            });
         }
+        return Ok(());
     }
+    // SAFETY: killpg has no memory safety requirements, the process group
+    // id is the pid of child since it was spawned in a new process group.
+    if unsafe { libc::killpg(pgid, libc::SIGKILL) } != 0 {
+        return Err(Error::Io(io::Error::last_os_error()));
+    }
+    Ok(())
 }
 
-pub fn execute(shell: &Shell) -> Result<Output, Error> {
-    debug!("Executing command: {}", shell.command);
-    Ok(Command::new("bash")
-        .arg("-c")
-        .arg(&shell.command)
-        .output()?)
+// Log each line read from reader in a new thread, returning the last lines read.
+fn stream<R>(reader: Option<R>, label: &str, quiet: bool) -> thread::JoinHandle<String>
+where
+    R: Read + Send + 'static,
+{
+    let label = label.to_string();
+    thread::spawn(move || {
+        let mut tail = VecDeque::with_capacity(TAIL_LINES);
+        if let Some(reader) = reader {
+            let mut reader = BufReader::new(reader);
+            let mut line = vec![];
+            while reader.read_until(b'\n', &mut line).unwrap_or(0) > 0 {
+                let text = String::from_utf8_lossy(&line).to_string();
+                if !quiet {
+                    info!("[{}] {}", label, text.trim_end());
+                }
+                if tail.len() == TAIL_LINES {
+                    tail.pop_front();
+                }
+                tail.push_back(text);
+                line.clear();
+            }
+        }
+        tail.into_iter().collect()
+    })
 }
 
 #[cfg(test)]
@@ -51,19 +516,233 @@ mod tests {
 
     #[test]
     fn test_multiple_commands() {
-        let output = execute(&Shell::new("echo foo bar")).unwrap();
+        let output = execute(&Shell::new("echo foo bar"), &Context::default()).unwrap();
         assert!(output.status.success());
-        assert_eq!("foo bar\n", String::from_utf8_lossy(&output.stdout));
+        assert_eq!("foo bar\n", output.stdout);
     }
 
     #[test]
     fn test_single_command() {
-        let output = execute(&Shell::new("ls")).unwrap();
+        let output = execute(&Shell::new("ls"), &Context::default()).unwrap();
         assert!(output.status.success());
     }
 
     #[test]
     fn test_invalid_command() {
-        assert_eq!(false, execute(&Shell::new("bla")).unwrap().status.success());
+        match execute(&Shell::new("bla"), &Context::default()) {
+            Err(Error::Failed { code, stderr, .. }) => {
+                assert_eq!(Some(127), code);
+                assert!(stderr.contains("bla"), "{}", stderr);
+            }
+            result => panic!("Expected command to fail: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_failed_command_message() {
+        let err = execute(&Shell::new("echo oops >&2; exit 3"), &Context::default()).unwrap_err();
+        assert_eq!(
+            "Command \"echo oops >&2; exit 3\" failed with exit code 3: oops",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn test_ignore_errors() {
+        let mut shell = Shell::new("exit 1");
+        shell.ignore_errors = true;
+        assert!(!execute(&shell, &Context::default())
+            .unwrap()
+            .status
+            .success());
+    }
+
+    #[test]
+    fn test_skip_reason() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut shell = Shell::new("ls");
+        assert_eq!(None, skip_reason(&shell, &Context::default()).unwrap());
+
+        shell.creates = Some(dir.path().join("missing").to_str().unwrap().to_string());
+        shell.onlyif = Some(String::from("true"));
+        shell.unless = Some(String::from("false"));
+        assert_eq!(None, skip_reason(&shell, &Context::default()).unwrap());
+
+        shell.onlyif = Some(String::from("false"));
+        assert_eq!(
+            Some(String::from("\"false\" failed")),
+            skip_reason(&shell, &Context::default()).unwrap()
+        );
+
+        shell.unless = Some(String::from("true"));
+        assert_eq!(
+            Some(String::from("\"true\" succeeded")),
+            skip_reason(&shell, &Context::default()).unwrap()
+        );
+
+        shell.creates = Some(dir.path().to_str().unwrap().to_string());
+        assert_eq!(
+            Some(format!("{} already exists", dir.path().display())),
+            skip_reason(&shell, &Context::default()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_working_dir_and_env() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+
+        let ctx = Context {
+            dir: Some(dir.path()),
+            ..Context::default()
+        };
+        let mut shell = Shell::new("echo $FOO; pwd");
+        shell.env.insert(String::from("FOO"), String::from("bar"));
+        let output = execute(&shell, &ctx).unwrap();
+        assert_eq!(format!("bar\n{}\n", dir.path().display()), output.stdout);
+
+        shell.cwd = Some(String::from("sub"));
+        let output = execute(&shell, &ctx).unwrap();
+        assert_eq!(
+            format!("bar\n{}\n", dir.path().join("sub").display()),
+            output.stdout
+        );
+    }
+
+    #[test]
+    fn test_interpreter() {
+        let mut shell = Shell::new("[[ -n bash ]] && echo bash");
+        assert!(execute(&shell, &Context::default()).is_ok());
+
+        shell.interpreter = Some(Interpreter::Sh);
+        shell.command = String::from("echo $0");
+        let output = execute(&shell, &Context::default()).unwrap();
+        assert_eq!("sh\n", output.stdout);
+    }
+
+    #[test]
+    fn test_script() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("script.sh"),
+            "#!/bin/sh -e\necho \"$0 $@\"\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("script.py"),
+            "import sys\nprint(sys.argv[1:])\n",
+        )
+        .unwrap();
+
+        let ctx = Context {
+            dir: Some(dir.path()),
+            ..Context::default()
+        };
+        let output = execute(&Shell::script("script.sh", &["foo", "bar"]), &ctx).unwrap();
+        assert_eq!(
+            format!("{} foo bar\n", dir.path().join("script.sh").display()),
+            output.stdout
+        );
+
+        let mut shell = Shell::script("script.py", &["foo"]);
+        shell.interpreter = Some(Interpreter::Python);
+        assert_eq!("['foo']\n", execute(&shell, &ctx).unwrap().stdout);
+    }
+
+    #[test]
+    fn test_script_on_change() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("script.sh"), "echo foo\n").unwrap();
+
+        let mut ctx = Context {
+            dir: Some(dir.path()),
+            ..Context::default()
+        };
+        let mut shell = Shell::script("script.sh", &[]);
+        shell.on_change = true;
+
+        assert_eq!(None, skip_reason(&shell, &ctx).unwrap());
+        let hash = script_hash(&shell, &ctx).unwrap().unwrap();
+        assert_eq!(state::digest(b"echo foo\n"), hash);
+        ctx.recorded = Some(&hash);
+        assert_eq!(
+            Some(String::from("script did not change")),
+            skip_reason(&shell, &ctx).unwrap()
+        );
+
+        fs::write(dir.path().join("script.sh"), "echo bar\n").unwrap();
+        assert_eq!(None, skip_reason(&shell, &ctx).unwrap());
+    }
+
+    #[test]
+    fn test_become() {
+        let dir = tempfile::tempdir().unwrap();
+        let escalation = crate::privilege::tests::stub(dir.path());
+        let ctx = Context {
+            escalation: Some(&escalation),
+            ..Context::default()
+        };
+
+        let mut shell = Shell::new("echo $FOO");
+        shell.become_user = Some(String::from("nobody"));
+        shell.env.insert(String::from("FOO"), String::from("bar"));
+        assert_eq!("bar\n", execute(&shell, &ctx).unwrap().stdout);
+        assert_eq!(
+            "-u nobody env FOO=bar bash -c echo $FOO\n",
+            fs::read_to_string(dir.path().join("args")).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_timeout() {
+        let mut shell = Shell::new("sleep 10 & wait");
+        shell.timeout = Some(1);
+
+        let start = Instant::now();
+        match execute(&shell, &Context::default()) {
+            Err(Error::Timeout { timeout, .. }) => assert_eq!(1, timeout),
+            result => panic!("Expected command to timeout: {:?}", result),
+        }
+        // The background sleep keeps stdout open unless the whole group is killed.
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_timeout_become() {
+        let dir = tempfile::tempdir().unwrap();
+        let escalation = crate::privilege::tests::stub(dir.path());
+        let ctx = Context {
+            escalation: Some(&escalation),
+            ..Context::default()
+        };
+
+        let mut shell = Shell::new("sleep 10 & wait");
+        shell.become_user = Some(String::from("nobody"));
+        shell.timeout = Some(1);
+
+        let start = Instant::now();
+        assert!(matches!(
+            execute(&shell, &ctx),
+            Err(Error::Timeout { timeout: 1, .. })
+        ));
+        assert!(start.elapsed() < Duration::from_secs(5));
+        // As root, kill is executed directly, without the escalation command.
+        if !crate::privilege::is_root() {
+            let args = fs::read_to_string(dir.path().join("args")).unwrap();
+            assert!(args
+                .lines()
+                .any(|line| line.starts_with("-u root env kill")));
+        }
+    }
+
+    #[test]
+    fn test_output_tail() {
+        let output = execute(&Shell::new("seq 1 100; echo err >&2"), &Context::default()).unwrap();
+        let lines: Vec<&str> = output.stdout.lines().collect();
+        assert_eq!(TAIL_LINES, lines.len());
+        assert_eq!(Some(&"81"), lines.first());
+        assert_eq!(Some(&"100"), lines.last());
+        assert_eq!("err\n", output.stderr);
     }
 }
@@ -0,0 +1,242 @@
+use crate::atomic;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Name of the file that keeps the state inside the state directory.
+pub const STATE_FILE: &str = "state.json";
+
+const LOCK_FILE: &str = "state.lock";
+
+#[derive(Debug)]
+pub enum Error {
+    /// io error reading or writing the state.
+    Io(io::Error),
+
+    /// State file is not valid.
+    Json(serde_json::Error),
+
+    /// Other run of ipa holds the lock of state directory.
+    Locked(PathBuf),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "Unable to access state: {}", e),
+            Error::Json(e) => write!(f, "Invalid state file: {}", e),
+            Error::Locked(path) => write!(f, "State {:?} is locked by other run of ipa", path),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(val: io::Error) -> Self {
+        Error::Io(val)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(val: serde_json::Error) -> Self {
+        Error::Json(val)
+    }
+}
+
+/// Directory of state following the XDG base directory specification,
+/// `$XDG_STATE_HOME/ipa` or `~/.local/state/ipa`.
+pub fn default_dir() -> Option<PathBuf> {
+    match env::var_os("XDG_STATE_HOME") {
+        Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir).join("ipa")),
+        _ => env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state/ipa")),
+    }
+}
+
+/// Hex encoded sha256 hash of content.
+pub fn digest(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Package,
+    Link,
+    Shell,
+}
+
+impl Kind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Kind::Package => "package",
+            Kind::Link => "link",
+            Kind::Shell => "shell",
+        }
+    }
+}
+
+/// Action that ipa applied successfully.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Record {
+    pub group: String,
+
+    /// Id of value inside its group.
+    pub id: String,
+
+    pub kind: Kind,
+
+    /// Package name, link destination or shell command.
+    pub target: String,
+
+    /// Expanded paths of the action, like the source and destination of link.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<PathBuf>,
+
+    /// Hash of the content of link source or of script.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+
+    /// Seconds since the Unix epoch when the action was applied.
+    pub timestamp: u64,
+}
+
+impl Record {
+    pub fn new(group: &str, id: &str, kind: Kind, target: &str) -> Self {
+        Record {
+            group: group.to_string(),
+            id: id.to_string(),
+            kind,
+            target: target.to_string(),
+            paths: vec![],
+            hash: None,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default(),
+        }
+    }
+
+    fn same_action(&self, other: &Record) -> bool {
+        self.group == other.group && self.kind == other.kind && self.target == other.target
+    }
+}
+
+/// Everything that ipa has applied in previous runs.
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct State {
+    #[serde(skip)]
+    path: PathBuf,
+
+    pub records: Vec<Record>,
+}
+
+impl State {
+    /// Load the state of file, or an empty state if it does not exists yet.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let mut state = match fs::read(path) {
+            Ok(content) => serde_json::from_slice(&content)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => State::default(),
+            Err(err) => return Err(Error::Io(err)),
+        };
+        state.path = path.to_path_buf();
+        Ok(state)
+    }
+
+    /// Previous record of the same action of record.
+    pub fn previous(&self, record: &Record) -> Option<&Record> {
+        self.records.iter().find(|r| r.same_action(record))
+    }
+
+    /// Add the record, replacing the previous record of the same action.
+    pub fn record(&mut self, record: Record) {
+        let previous = self.records.iter().position(|r| r.same_action(&record));
+        match previous {
+            Some(i) => self.records[i] = record,
+            None => self.records.push(record),
+        }
+    }
+
+    /// Write the state to its file, see `atomic::write`.
+    pub fn save(&self) -> Result<(), Error> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        atomic::write(&self.path, &serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Exclusive lock of the state directory, released when dropped.
+#[derive(Debug)]
+pub struct Lock {
+    _file: File,
+}
+
+impl Lock {
+    /// Lock the state directory, failing if other run of ipa holds it.
+    pub fn acquire(dir: &Path) -> Result<Self, Error> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(LOCK_FILE);
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)?;
+        // SAFETY: the file descriptor is valid while file is open.
+        if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+            let err = io::Error::last_os_error();
+            if err.kind() == io::ErrorKind::WouldBlock {
+                return Err(Error::Locked(path));
+            }
+            return Err(Error::Io(err));
+        }
+        Ok(Lock { _file: file })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(STATE_FILE);
+
+        let mut state = State::load(&path).unwrap();
+        assert!(state.records.is_empty());
+
+        let mut record = Record::new("dev", "editor", Kind::Link, "~/.config/nvim");
+        record.paths = vec![PathBuf::from("/src"), PathBuf::from("/dst")];
+        state.record(record.clone());
+        state.record(Record::new("dev", "editor", Kind::Package, "neovim"));
+        record.hash = Some(digest(b"foo"));
+        state.record(record.clone());
+        state.save().unwrap();
+
+        let state = State::load(&path).unwrap();
+        assert_eq!(2, state.records.len());
+        assert_eq!(record, state.records[0]);
+        let other = Record::new("dev", "other", Kind::Link, "~/.config/nvim");
+        assert_eq!(Some(&record), state.previous(&other));
+        assert_eq!(
+            None,
+            state.previous(&Record::new("dev", "x", Kind::Shell, "ls"))
+        );
+        assert!(!dir.path().join("state.json.tmp").exists());
+    }
+
+    #[test]
+    fn test_lock() {
+        let dir = tempdir().unwrap();
+        let lock = Lock::acquire(dir.path()).unwrap();
+        assert!(matches!(Lock::acquire(dir.path()), Err(Error::Locked(_))));
+        drop(lock);
+        assert!(Lock::acquire(dir.path()).is_ok());
+    }
+}
@@ -0,0 +1,241 @@
+use crate::{
+    config::Config,
+    pacman::PackageManagement,
+    shell,
+    state::Kind,
+    symlink::{self, Status},
+};
+
+const GREEN: &str = "\x1b[32m";
+const RED: &str = "\x1b[31m";
+const RESET: &str = "\x1b[0m";
+
+/// Result of comparing an action of a value to the current machine.
+#[derive(Debug, PartialEq)]
+pub struct Check {
+    pub group: String,
+
+    /// Id of value inside its group.
+    pub id: String,
+
+    pub kind: Kind,
+
+    /// Package name, link destination or path created by shell command.
+    pub target: String,
+
+    pub in_sync: bool,
+
+    /// Description of the status, like `installed` or `missing`.
+    pub status: String,
+}
+
+/// Check every value of config without changing anything: if packages are
+/// installed, explicitly or as dependencies of others, if links exists and point to their source and if the paths
+/// of the `creates` guard of shell commands exists. Shell commands without
+/// `creates` can't be checked, so they are not included. Actions that can't
+/// be checked, like links whose source is missing, are out of sync.
+pub fn check<P>(config: &Config, pacman: &P) -> Vec<Check>
+where
+    P: PackageManagement,
+{
+    let ctx = shell::Context {
+        dir: config.dir.as_deref(),
+        ..shell::Context::default()
+    };
+    let mut checks = vec![];
+    for group in config.groups.iter() {
+        for value in group.values.iter() {
+            let id = value.id();
+            let check = |kind, target: &str, in_sync, status: &str| Check {
+                group: group.name.clone(),
+                id: id.clone(),
+                kind,
+                target: target.to_string(),
+                in_sync,
+                status: status.to_string(),
+            };
+
+            for package in value.packages.iter() {
+                let (in_sync, status) = match pacman.installed(package) {
+                    Ok(true) => (true, String::from("installed")),
+                    Ok(false) => (false, String::from("missing")),
+                    Err(err) => (false, format!("error: {}", err)),
+                };
+                checks.push(check(Kind::Package, &package.name, in_sync, &status));
+            }
+
+            if let Some(ref link) = value.link {
+                let (in_sync, status) = match symlink::status(link) {
+                    Ok(statuses) => (
+                        statuses.iter().all(|(_, status)| *status == Status::Linked),
+                        match statuses.as_slice() {
+                            [(_, status)] => status.to_string(),
+                            _ => summarize(&statuses),
+                        },
+                    ),
+                    Err(_) if link.paths().is_ok_and(|(src, _)| !src.exists()) => {
+                        (false, String::from("source missing"))
+                    }
+                    Err(err) => (false, format!("error: {}", err)),
+                };
+                checks.push(check(Kind::Link, &link.dst, in_sync, &status));
+            }
+
+            if let Some(ref shell) = value.shell {
+                match shell::creates(shell, &ctx) {
+                    Ok(Some(path)) => {
+                        let (in_sync, status) = match path.exists() {
+                            true => (true, "created"),
+                            false => (false, "not created"),
+                        };
+                        let target = path.display().to_string();
+                        checks.push(check(Kind::Shell, &target, in_sync, status));
+                    }
+                    Ok(None) => {}
+                    Err(err) => {
+                        let status = format!("error: {}", err);
+                        checks.push(check(Kind::Shell, &shell.command_line(), false, &status));
+                    }
+                }
+            }
+        }
+    }
+    checks
+}
+
+// Describe the statuses of the files of a directory link, like `3 linked, 1 missing`.
+fn summarize(statuses: &[(std::path::PathBuf, Status)]) -> String {
+    let count = |f: fn(&Status) -> bool| statuses.iter().filter(|(_, s)| f(s)).count();
+    let counts = [
+        (count(|s| *s == Status::Linked), "linked"),
+        (count(|s| *s == Status::Missing), "missing"),
+        (
+            count(|s| matches!(s, Status::WrongTarget(_))),
+            "wrong target",
+        ),
+        (count(|s| *s == Status::Conflict), "conflicting"),
+    ];
+    let parts: Vec<String> = counts
+        .iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, name)| format!("{} {}", count, name))
+        .collect();
+    if parts.is_empty() {
+        return String::from("empty");
+    }
+    parts.join(", ")
+}
+
+/// Render the checks as a table grouped by group, coloring the status
+/// of each row if `color` is set.
+pub fn render(checks: &[Check], color: bool) -> String {
+    let width = |f: fn(&Check) -> usize, header: &str| {
+        checks
+            .iter()
+            .map(f)
+            .chain(Some(header.len()))
+            .max()
+            .unwrap_or_default()
+    };
+    let group = width(|c| c.group.len(), "group");
+    let id = width(|c| c.id.len(), "value");
+    let kind = "package".len();
+    let target = width(|c| c.target.len(), "target");
+
+    let mut table = format!(
+        "{:group$}  {:id$}  {:kind$}  {:target$}  status\n",
+        "group",
+        "value",
+        "kind",
+        "target",
+        group = group,
+        id = id,
+        kind = kind,
+        target = target
+    );
+    for check in checks.iter() {
+        let status = match (color, check.in_sync) {
+            (false, _) => check.status.clone(),
+            (true, true) => format!("{}{}{}", GREEN, check.status, RESET),
+            (true, false) => format!("{}{}{}", RED, check.status, RESET),
+        };
+        table.push_str(&format!(
+            "{:group$}  {:id$}  {:kind$}  {:target$}  {}\n",
+            check.group,
+            check.id,
+            check.kind.as_str(),
+            check.target,
+            status,
+            group = group,
+            id = id,
+            kind = kind,
+            target = target
+        ));
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pacman::FakePacman;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_check() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        std::fs::write(&src, "foo").unwrap();
+        std::fs::write(dir.path().join("conflict"), "bar").unwrap();
+
+        let content = format!(
+            "
+dev:
+  - package: [git, neovim]
+  - link: {src} -> {dir}/conflict
+  - link: {dir}/missing -> {dir}/dst
+  - shell:
+      command: touch created
+      creates: {dir}/created
+  - shell: echo not checked
+  - shell:
+      command: touch invalid
+      creates: $IPA_UNDEFINED_VARIABLE/created
+",
+            src = src.display(),
+            dir = dir.path().display(),
+        );
+        // Loaded from file like the CLI does, which only warns about the missing source.
+        let config_file = dir.path().join("dotfiles.yml");
+        std::fs::write(&config_file, content).unwrap();
+        let config = Config::load(&config_file).unwrap();
+        let checks = check(&config, &FakePacman::with_installed(&["git"], &[]));
+
+        let statuses: Vec<(Kind, bool, &str)> = checks
+            .iter()
+            .map(|c| (c.kind, c.in_sync, c.status.as_str()))
+            .collect();
+        assert_eq!(
+            vec![
+                (Kind::Package, true, "installed"),
+                (Kind::Package, false, "missing"),
+                (Kind::Link, false, "conflicting file"),
+                (Kind::Link, false, "source missing"),
+                (Kind::Shell, false, "not created"),
+                (
+                    Kind::Shell,
+                    false,
+                    "error: Unable to expand path: error looking key 'IPA_UNDEFINED_VARIABLE' up: environment variable not found"
+                ),
+            ],
+            statuses
+        );
+
+        let table = render(&checks[..1], true);
+        assert_eq!(
+            "group  value       kind     target  status\n\
+             dev    git neovim  package  git     \x1b[32minstalled\x1b[0m\n",
+            table
+        );
+    }
+}
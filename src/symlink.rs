@@ -2,7 +2,12 @@ use log::{debug, warn};
 use serde::{Deserialize, Serialize};
 use shellexpand::LookupError;
 use std::env::VarError;
-use std::{fs, io, os::unix, path::Path};
+use std::{
+    fs, io,
+    os::unix,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
 #[derive(Debug)]
 pub enum Error {
@@ -35,6 +40,7 @@ impl From<io::Error> for Error {
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
 pub struct SymLink {
     pub dst: String,
 
@@ -58,7 +64,62 @@ impl SymLink {
     }
 }
 
-pub fn symlink(link: &SymLink) -> Result<(), Error> {
+impl FromStr for SymLink {
+    type Err = String;
+
+    /// Parse the shorthand form `link: "~/.dotfiles/git/gitconfig -> ~/.gitconfig"`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split("->");
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(src), Some(dst), None) if !src.trim().is_empty() && !dst.trim().is_empty() => Ok(
+                SymLink::new(dst.trim(), src.trim(), false, default_create()),
+            ),
+            _ => Err(format!(
+                "invalid link shorthand \"{}\", expected \"<src> -> <dst>\"",
+                s
+            )),
+        }
+    }
+}
+
+impl SymLink {
+    /// Expand the source and destination paths of link.
+    pub fn paths(&self) -> Result<(PathBuf, PathBuf), Error> {
+        Ok((
+            PathBuf::from(shellexpand::full(&self.src)?.as_ref()),
+            PathBuf::from(shellexpand::full(&self.dst)?.as_ref()),
+        ))
+    }
+}
+
+/// Number of symbolic links by what happened to them.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Linked {
+    pub created: usize,
+    pub relinked: usize,
+    pub unchanged: usize,
+
+    /// Existing files kept in place of the link, since relink is disabled.
+    pub conflicts: usize,
+}
+
+impl Linked {
+    /// Check if any link was created or replaced.
+    pub fn changed(&self) -> bool {
+        self.created > 0 || self.relinked > 0
+    }
+
+    fn add(&mut self, other: Linked) {
+        self.created += other.created;
+        self.relinked += other.relinked;
+        self.unchanged += other.unchanged;
+        self.conflicts += other.conflicts;
+    }
+}
+
+/// Create the symbolic link, returning how many links were created, replaced
+/// or already existed.
+pub fn symlink(link: &SymLink) -> Result<Linked, Error> {
     let mut src = String::new();
     let mut dst = String::new();
     symlink_path(
@@ -69,7 +130,8 @@ pub fn symlink(link: &SymLink) -> Result<(), Error> {
     )
 }
 
-fn symlink_path(src: &Path, dst: &Path, relink: bool, create: bool) -> Result<(), Error> {
+fn symlink_path(src: &Path, dst: &Path, relink: bool, create: bool) -> Result<Linked, Error> {
+    let mut linked = Linked::default();
     if !src.exists() {
         return Err(Error::Io(io::Error::new(
             io::ErrorKind::NotFound,
@@ -82,12 +144,24 @@ fn symlink_path(src: &Path, dst: &Path, relink: bool, create: bool) -> Result<()
     }
 
     if dst.exists() {
+        if fs::read_link(dst).ok().as_deref() == Some(src) {
+            debug!("Symbolic link {:?} is up to date", dst);
+            linked.unchanged = 1;
+            return Ok(linked);
+        }
         if !relink {
             warn!("Symbolic link {:?} already exists", dst);
-            return Ok(());
+            linked.conflicts = 1;
+            return Ok(linked);
         }
         warn!("Relinking {:?}", dst);
         fs::remove_file(dst)?;
+        linked = Linked {
+            relinked: 1,
+            ..Linked::default()
+        };
+    } else {
+        linked.created = 1;
     }
 
     if create {
@@ -101,10 +175,11 @@ fn symlink_path(src: &Path, dst: &Path, relink: bool, create: bool) -> Result<()
 
     debug!("Linking {:?} in {:?}", src, dst);
     unix::fs::symlink(src, dst)?;
-    Ok(())
+    Ok(linked)
 }
 
-fn symlink_dir(src: &Path, dst: &Path, relink: bool, create: bool) -> Result<(), Error> {
+fn symlink_dir(src: &Path, dst: &Path, relink: bool, create: bool) -> Result<Linked, Error> {
+    let mut linked = Linked::default();
     debug!("Create symbolic link to all files into {:?}", src);
     for entry in fs::read_dir(src)? {
         let entry = entry?;
@@ -120,17 +195,84 @@ fn symlink_dir(src: &Path, dst: &Path, relink: bool, create: bool) -> Result<(),
                 if !dst_dir.exists() {
                     fs::create_dir(&dst_dir)?;
                 }
-                symlink_dir(entry.path().as_path(), dst_dir.as_path(), relink, create)?;
+                linked.add(symlink_dir(
+                    entry.path().as_path(),
+                    dst_dir.as_path(),
+                    relink,
+                    create,
+                )?);
             }
         } else if let Some(name) = entry.path().file_name() {
-            symlink_path(
+            linked.add(symlink_path(
                 entry.path().as_path(),
                 dst.join(name).as_path(),
                 relink,
                 create,
-            )?;
+            )?);
+        }
+    }
+    Ok(linked)
+}
+
+/// State of the destination of a link compared to its source.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Status {
+    /// Destination is a link to the source.
+    Linked,
+
+    /// Destination does not exists.
+    Missing,
+
+    /// Destination is a link to other file.
+    WrongTarget(PathBuf),
+
+    /// Destination is a file that is not a link.
+    Conflict,
+}
+
+impl std::fmt::Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Status::Linked => write!(f, "linked"),
+            Status::Missing => write!(f, "missing"),
+            Status::WrongTarget(target) => write!(f, "wrong target {:?}", target),
+            Status::Conflict => write!(f, "conflicting file"),
+        }
+    }
+}
+
+/// Compare each destination of link to its source without changing them.
+/// Files of a directory source are compared one by one, like they are linked.
+pub fn status(link: &SymLink) -> Result<Vec<(PathBuf, Status)>, Error> {
+    let (src, dst) = link.paths()?;
+    let mut statuses = vec![];
+    status_path(&src, &dst, &mut statuses)?;
+    Ok(statuses)
+}
+
+fn status_path(src: &Path, dst: &Path, statuses: &mut Vec<(PathBuf, Status)>) -> Result<(), Error> {
+    if !src.exists() {
+        return Err(Error::Io(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("source file of link does not exists: {:?}", src),
+        )));
+    }
+
+    if src.is_dir() && dst.is_dir() && !dst.symlink_metadata()?.file_type().is_symlink() {
+        for entry in fs::read_dir(src)? {
+            let entry = entry?;
+            status_path(&entry.path(), &dst.join(entry.file_name()), statuses)?;
         }
+        return Ok(());
     }
+
+    let status = match fs::read_link(dst) {
+        Ok(target) if target == src => Status::Linked,
+        Ok(target) => Status::WrongTarget(target),
+        Err(_) if dst.symlink_metadata().is_ok() => Status::Conflict,
+        Err(_) => Status::Missing,
+    };
+    statuses.push((dst.to_path_buf(), status));
     Ok(())
 }
 
@@ -167,7 +309,19 @@ mod tests {
             true,
         );
 
-        assert!(symlink(&link).is_ok());
+        assert_eq!(1, symlink(&link).unwrap().created);
+        assert_eq!(1, symlink(&link).unwrap().unchanged);
+
+        let conflict = dst_dir.path().join("conflict");
+        std::fs::write(&conflict, "bar").unwrap();
+        let link = SymLink::new(
+            conflict.to_str().unwrap(),
+            src_path_config.to_str().unwrap(),
+            false,
+            false,
+        );
+        let linked = symlink(&link).unwrap();
+        assert_eq!((0, 1), (linked.unchanged, linked.conflicts));
     }
 
     #[test]
@@ -176,6 +330,50 @@ mod tests {
         assert!(symlink(&link).is_err());
     }
 
+    #[test]
+    fn test_status() {
+        let src_dir = tempdir().unwrap();
+        let dst_dir = tempdir().unwrap();
+        for name in ["linked", "missing", "wrong", "conflict"].iter() {
+            std::fs::File::create(src_dir.path().join(name)).unwrap();
+        }
+        let link = |name: &str| {
+            SymLink::new(
+                dst_dir.path().join(name).to_str().unwrap(),
+                src_dir.path().join(name).to_str().unwrap(),
+                false,
+                true,
+            )
+        };
+
+        symlink(&link("linked")).unwrap();
+        unix::fs::symlink(src_dir.path().join("linked"), dst_dir.path().join("wrong")).unwrap();
+        std::fs::File::create(dst_dir.path().join("conflict")).unwrap();
+
+        let statuses = |name: &str| -> Vec<Status> {
+            status(&link(name))
+                .unwrap()
+                .into_iter()
+                .map(|(_, status)| status)
+                .collect()
+        };
+        assert_eq!(vec![Status::Linked], statuses("linked"));
+        assert_eq!(vec![Status::Missing], statuses("missing"));
+        assert_eq!(
+            vec![Status::WrongTarget(src_dir.path().join("linked"))],
+            statuses("wrong")
+        );
+        assert_eq!(vec![Status::Conflict], statuses("conflict"));
+
+        let dir = SymLink::new(
+            dst_dir.path().to_str().unwrap(),
+            src_dir.path().to_str().unwrap(),
+            false,
+            true,
+        );
+        assert_eq!(4, status(&dir).unwrap().len());
+    }
+
     #[test]
     fn test_link_dir() {
         let src_dir = tempdir().unwrap();
@@ -220,7 +418,7 @@ mod tests {
             false,
         );
 
-        symlink(&link).unwrap();
+        assert_eq!(1, symlink(&link).unwrap().relinked);
 
         let is_symlink = std::fs::symlink_metadata(dst_path_config.as_path())
             .unwrap()
@@ -0,0 +1,986 @@
+use crate::{
+    config::{self, GroupOptions, Hooks, Values},
+    pacman::Package,
+    shell::Shell,
+    symlink::SymLink,
+};
+use serde::{de, Deserialize, Deserializer};
+use std::{
+    collections::HashMap,
+    fmt,
+    iter::Peekable,
+    path::{Path, PathBuf},
+    str::FromStr,
+    vec::IntoIter,
+};
+use yaml_rust::{
+    parser::{Event, MarkedEventReceiver, Parser},
+    scanner::{Marker, TScalarStyle},
+};
+
+/// Names of the fields of a struct deserialized with serde, after renames,
+/// so the known keys of config always match the structs that read them.
+fn fields<T>() -> &'static [&'static str]
+where
+    T: for<'de> Deserialize<'de>,
+{
+    match T::deserialize(FieldsDeserializer) {
+        Err(Fields(fields)) => fields,
+        Ok(_) => &[],
+    }
+}
+
+// Error returned by `FieldsDeserializer` with the fields of struct.
+#[derive(Debug)]
+struct Fields(&'static [&'static str]);
+
+impl fmt::Display for Fields {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "fields: {}", self.0.join(", "))
+    }
+}
+
+impl std::error::Error for Fields {}
+
+impl de::Error for Fields {
+    fn custom<T: fmt::Display>(_: T) -> Self {
+        Fields(&[])
+    }
+}
+
+// Deserializer that never produces a value, only the fields of a struct.
+struct FieldsDeserializer;
+
+impl<'de> Deserializer<'de> for FieldsDeserializer {
+    type Error = Fields;
+
+    fn deserialize_any<V>(self, _: V) -> Result<V::Value, Fields>
+    where
+        V: de::Visitor<'de>,
+    {
+        Err(Fields(&[]))
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _: &'static str,
+        fields: &'static [&'static str],
+        _: V,
+    ) -> Result<V::Value, Fields>
+    where
+        V: de::Visitor<'de>,
+    {
+        Err(Fields(fields))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map enum identifier ignored_any
+    }
+}
+
+/// A problem found in the config file.
+#[derive(Debug, PartialEq)]
+pub struct Problem {
+    pub file: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+
+    /// Problem that does not prevent loading the config, like a source of
+    /// link that does not exists, since it only fails its own entry.
+    pub warning: bool,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}",
+            self.file.display(),
+            self.line,
+            self.column,
+            self.message
+        )
+    }
+}
+
+/// Validate the content of config file, returning every problem found.
+///
+/// The validation checks for unknown keys, empty required fields and
+/// sources of links that does not exists, reported as warnings. Type
+/// errors are left to the deserialization of config.
+pub fn validate(content: &str, file: &Path) -> Vec<Problem> {
+    let mut validator = Validator {
+        file,
+        groups: vec![],
+        ids: vec![],
+        handlers: vec![],
+        problems: vec![],
+    };
+    match parse(content) {
+        Ok(Some(root)) => validator.root(&root),
+        Ok(None) => {}
+        Err((mark, message)) => validator.problem(&mark, message),
+    }
+    validator
+        .problems
+        .sort_by_key(|problem| (problem.line, problem.column));
+    validator.problems
+}
+
+#[derive(Clone)]
+enum Kind {
+    /// Scalar value and if it is null.
+    Scalar(String, bool),
+    Sequence(Vec<Node>),
+    Mapping(Vec<(Node, Node)>),
+}
+
+#[derive(Clone)]
+struct Node {
+    kind: Kind,
+    mark: Marker,
+}
+
+impl Node {
+    fn is_null(&self) -> bool {
+        matches!(self.kind, Kind::Scalar(_, true))
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self.kind {
+            Kind::Scalar(ref value, false) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+struct EventList(Vec<(Event, Marker)>);
+
+impl MarkedEventReceiver for EventList {
+    fn on_event(&mut self, ev: Event, mark: Marker) {
+        self.0.push((ev, mark));
+    }
+}
+
+type Events = Peekable<IntoIter<(Event, Marker)>>;
+
+// Parse only the first document of content keeping the position of each node.
+fn parse(content: &str) -> Result<Option<Node>, (Marker, String)> {
+    let mut events = EventList(vec![]);
+    if let Err(err) = Parser::new(content.chars()).load(&mut events, false) {
+        // Display of ScanError already includes the position.
+        let message = err.to_string();
+        let message = message.split(" at line ").next().unwrap_or_default();
+        return Err((*err.marker(), message.to_string()));
+    }
+
+    let mut events = events.0.into_iter().peekable();
+    loop {
+        match events.next() {
+            Some((Event::DocumentStart, _)) => break,
+            Some(_) => continue,
+            None => return Ok(None),
+        }
+    }
+    if let Some((Event::DocumentEnd, _)) = events.peek() {
+        return Ok(None);
+    }
+    Ok(Some(build(&mut events, &mut HashMap::new())))
+}
+
+fn build(events: &mut Events, anchors: &mut HashMap<usize, Node>) -> Node {
+    let (event, mark) = events.next().expect("unbalanced yaml events");
+    let (kind, anchor) = match event {
+        Event::Alias(id) => {
+            if let Some(node) = anchors.get(&id) {
+                return node.clone();
+            }
+            (Kind::Scalar(String::new(), true), 0)
+        }
+        Event::Scalar(value, style, anchor, _) => {
+            let null = style == TScalarStyle::Plain
+                && matches!(value.as_str(), "" | "~" | "null" | "Null" | "NULL");
+            (Kind::Scalar(value, null), anchor)
+        }
+        Event::SequenceStart(anchor) => {
+            let mut items = vec![];
+            while !matches!(events.peek(), Some((Event::SequenceEnd, _)) | None) {
+                items.push(build(events, anchors));
+            }
+            events.next();
+            (Kind::Sequence(items), anchor)
+        }
+        Event::MappingStart(anchor) => {
+            let mut pairs = vec![];
+            while !matches!(events.peek(), Some((Event::MappingEnd, _)) | None) {
+                let key = build(events, anchors);
+                let value = build(events, anchors);
+                pairs.push((key, value));
+            }
+            events.next();
+            (Kind::Mapping(pairs), anchor)
+        }
+        _ => (Kind::Scalar(String::new(), true), 0),
+    };
+    let node = Node { kind, mark };
+    if anchor > 0 {
+        anchors.insert(anchor, node.clone());
+    }
+    node
+}
+
+struct Validator<'a> {
+    file: &'a Path,
+    groups: Vec<String>,
+
+    /// Ids of the values of each group, see `Values::id`.
+    ids: Vec<(String, Vec<String>)>,
+    handlers: Vec<String>,
+    problems: Vec<Problem>,
+}
+
+impl<'a> Validator<'a> {
+    fn problem(&mut self, mark: &Marker, message: String) {
+        self.problems.push(Problem {
+            file: self.file.to_path_buf(),
+            line: mark.line(),
+            column: mark.col() + 1,
+            message,
+            warning: false,
+        });
+    }
+
+    fn warning(&mut self, mark: &Marker, message: String) {
+        self.problem(mark, message);
+        if let Some(problem) = self.problems.last_mut() {
+            problem.warning = true;
+        }
+    }
+
+    fn root(&mut self, node: &Node) {
+        match node.kind {
+            Kind::Mapping(ref groups) => {
+                for (key, value) in groups.iter() {
+                    let name = key.as_str().unwrap_or_default().to_string();
+                    if name == config::HANDLERS {
+                        if let Kind::Mapping(ref handlers) = value.kind {
+                            self.handlers = handlers
+                                .iter()
+                                .map(|(k, _)| k.as_str().unwrap_or_default().to_string())
+                                .collect();
+                        }
+                    }
+                    if name == config::HOOKS || name == config::HANDLERS {
+                        continue;
+                    }
+                    if self.groups.contains(&name) {
+                        self.problem(&key.mark, format!("duplicated group {}", name));
+                    }
+                    self.ids.push((name.clone(), group_ids(value)));
+                    self.groups.push(name);
+                }
+                for (name, values) in groups.iter() {
+                    match name.as_str() {
+                        Some(config::HOOKS) => self.hooks(values),
+                        Some(config::HANDLERS) => self.handlers(values),
+                        _ => self.group(name, values),
+                    }
+                }
+            }
+            _ => self.problem(&node.mark, String::from("expected a mapping of groups")),
+        }
+    }
+
+    fn group(&mut self, name: &Node, node: &Node) {
+        match node.kind {
+            Kind::Sequence(_) => self.entries(node),
+            Kind::Mapping(ref pairs) => {
+                self.keys(pairs, fields::<GroupOptions>());
+                for (key, value) in pairs.iter() {
+                    match key.as_str() {
+                        Some("after") => self.group_names(value),
+                        Some("requires") => self.targets(value),
+                        Some("hooks") => self.hooks(value),
+                        Some("tags") => self.tags(value),
+                        Some("values") => self.entries(value),
+                        _ => {}
+                    }
+                }
+            }
+            _ => self.problem(
+                &node.mark,
+                format!(
+                    "group {} must be a list of entries or a mapping",
+                    name.as_str().unwrap_or_default()
+                ),
+            ),
+        }
+    }
+
+    // Check the entries of group, and that their ids are unique. Entries
+    // without a name use the id derived from them, see `Values::id`.
+    fn entries(&mut self, node: &Node) {
+        if let Kind::Sequence(ref entries) = node.kind {
+            let mut ids = vec![];
+            for entry in entries.iter() {
+                if let Kind::Mapping(ref pairs) = entry.kind {
+                    if let Some(name) = field(pairs, "name") {
+                        let value = name.as_str().unwrap_or_default().to_string();
+                        if ids.contains(&value) {
+                            self.problem(&name.mark, format!("duplicated name {}", value));
+                        }
+                        ids.push(value);
+                    } else if let Some((key, id)) = entry_id(pairs) {
+                        if ids.contains(&id) {
+                            self.problem(
+                                &key.mark,
+                                format!("duplicated id {}, add a name to tell them apart", id),
+                            );
+                        }
+                        ids.push(id);
+                    }
+                }
+                self.entry(entry);
+            }
+        }
+    }
+
+    fn hooks(&mut self, node: &Node) {
+        match node.kind {
+            Kind::Mapping(ref pairs) => {
+                self.keys(pairs, fields::<Hooks>());
+                for (key, value) in pairs.iter() {
+                    if let Some("before") | Some("after") = key.as_str() {
+                        match value.kind {
+                            Kind::Sequence(ref shells) => {
+                                for shell in shells.iter() {
+                                    self.shell(shell);
+                                }
+                            }
+                            _ => self.shell(value),
+                        }
+                    }
+                }
+            }
+            _ => self.problem(&node.mark, String::from("hooks must be a mapping")),
+        }
+    }
+
+    fn handlers(&mut self, node: &Node) {
+        match node.kind {
+            Kind::Mapping(ref handlers) => {
+                for (_, shell) in handlers.iter() {
+                    self.shell(shell);
+                }
+            }
+            _ => self.problem(&node.mark, String::from("handlers must be a mapping")),
+        }
+    }
+
+    // Check that the handlers notified by an entry exists.
+    fn notify(&mut self, node: &Node) {
+        let names = match node.kind {
+            Kind::Sequence(ref names) => names.iter().collect(),
+            _ => vec![node],
+        };
+        for name in names {
+            let handler = name.as_str().unwrap_or_default();
+            if !self.handlers.iter().any(|h| h == handler) {
+                self.problem(&name.mark, format!("unknown handler {}", handler));
+            }
+        }
+    }
+
+    fn tags(&mut self, node: &Node) {
+        let tags = match node.kind {
+            Kind::Sequence(ref tags) => tags.iter().collect(),
+            _ => vec![node],
+        };
+        for tag in tags {
+            if tag.as_str().is_none_or(str::is_empty) {
+                self.problem(&tag.mark, String::from("tag must be a non empty string"));
+            }
+        }
+    }
+
+    // Check that the groups referenced by other group exists.
+    fn group_names(&mut self, node: &Node) {
+        if let Kind::Sequence(ref names) = node.kind {
+            for name in names.iter() {
+                let group = name.as_str().unwrap_or_default();
+                if !self.groups.iter().any(|g| g == group) {
+                    self.problem(&name.mark, format!("unknown group {}", group));
+                }
+            }
+        }
+    }
+
+    // Check that the groups of targets like `group` or `group/value` exists.
+    // Ids of values are only known after deserializing them, so they are
+    // checked when the values are configured.
+    fn targets(&mut self, node: &Node) {
+        if let Kind::Sequence(ref targets) = node.kind {
+            for target in targets.iter() {
+                let (group, id) = config::split_target(target.as_str().unwrap_or_default());
+                if !self.groups.iter().any(|g| g == group) {
+                    self.problem(&target.mark, format!("unknown group {}", group));
+                    continue;
+                }
+                if let Some(id) = id {
+                    let known = self
+                        .ids
+                        .iter()
+                        .any(|(g, ids)| g == group && ids.iter().any(|i| i == id));
+                    if !known {
+                        self.problem(&target.mark, format!("unknown value {}/{}", group, id));
+                    }
+                }
+            }
+        }
+    }
+
+    fn entry(&mut self, node: &Node) {
+        let pairs = match node.kind {
+            Kind::Mapping(ref pairs) => pairs,
+            _ => {
+                return self.problem(&node.mark, String::from("entry must be a mapping"));
+            }
+        };
+        self.keys(pairs, fields::<Values>());
+        for (key, value) in pairs.iter() {
+            match key.as_str() {
+                Some("package") => self.packages(value),
+                Some("link") => self.link(value),
+                Some("shell") => self.shell(value),
+                Some("requires") => self.targets(value),
+                Some("notify") => self.notify(value),
+                Some("tags") => self.tags(value),
+                _ => {}
+            }
+        }
+    }
+
+    fn packages(&mut self, node: &Node) {
+        match node.kind {
+            Kind::Sequence(ref packages) => {
+                for package in packages.iter() {
+                    self.package(package);
+                }
+            }
+            _ => self.package(node),
+        }
+    }
+
+    fn package(&mut self, node: &Node) {
+        match node.kind {
+            Kind::Scalar(ref value, false) => self.shorthand::<Package>(value, &node.mark),
+            Kind::Mapping(ref pairs) => {
+                self.keys(pairs, fields::<Package>());
+                self.required(node, pairs, "name");
+            }
+            _ => {}
+        }
+    }
+
+    fn link(&mut self, node: &Node) {
+        match node.kind {
+            Kind::Scalar(ref value, false) => {
+                if let Ok(link) = SymLink::from_str(value) {
+                    self.source(&link.src, &node.mark);
+                } else {
+                    self.shorthand::<SymLink>(value, &node.mark);
+                }
+            }
+            Kind::Mapping(ref pairs) => {
+                self.keys(pairs, fields::<SymLink>());
+                if let Some(src) = self.required(node, pairs, "src") {
+                    self.source(src.as_str().unwrap_or_default(), &src.mark);
+                }
+                self.required(node, pairs, "dst");
+            }
+            _ => {}
+        }
+    }
+
+    fn shell(&mut self, node: &Node) {
+        match node.kind {
+            Kind::Scalar(ref value, false) => self.shorthand::<Shell>(value, &node.mark),
+            Kind::Mapping(ref pairs) => {
+                self.keys(pairs, fields::<Shell>());
+                let key = |name| pairs.iter().find(|(k, _)| k.as_str() == Some(name));
+                match (key("command"), key("script")) {
+                    (Some(_), Some((script, _))) => self.problem(
+                        &script.mark,
+                        String::from("only one of \"command\" or \"script\" can be used"),
+                    ),
+                    (None, Some(_)) => {
+                        if let Some(script) = self.required(node, pairs, "script") {
+                            self.script(script.as_str().unwrap_or_default(), &script.mark);
+                        }
+                    }
+                    _ => {
+                        self.required(node, pairs, "command");
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn shorthand<T>(&mut self, value: &str, mark: &Marker)
+    where
+        T: FromStr<Err = String>,
+    {
+        if let Err(err) = T::from_str(value) {
+            self.problem(mark, err);
+        }
+    }
+
+    // Check that all keys of mapping are known.
+    fn keys(&mut self, pairs: &[(Node, Node)], known: &[&str]) {
+        for (key, _) in pairs.iter() {
+            let name = key.as_str().unwrap_or_default();
+            if known.contains(&name) {
+                continue;
+            }
+            let message = match suggest(name, known) {
+                Some(suggestion) => {
+                    format!("unknown key \"{}\", did you mean \"{}\"?", name, suggestion)
+                }
+                None => format!(
+                    "unknown key \"{}\", expected one of: {}",
+                    name,
+                    known.join(", ")
+                ),
+            };
+            self.problem(&key.mark, message);
+        }
+    }
+
+    // Check that a required key is present and not empty, returning its value.
+    fn required<'n>(
+        &mut self,
+        node: &Node,
+        pairs: &'n [(Node, Node)],
+        key: &str,
+    ) -> Option<&'n Node> {
+        match pairs.iter().find(|(k, _)| k.as_str() == Some(key)) {
+            None => {
+                // Mark of block mappings points to its end, so use the first key instead.
+                let mark = pairs.first().map_or(node.mark, |(k, _)| k.mark);
+                self.problem(&mark, format!("missing required key \"{}\"", key));
+                None
+            }
+            Some((k, value)) if value.is_null() => {
+                self.problem(&k.mark, format!("\"{}\" can not be empty", key));
+                None
+            }
+            Some((_, value)) if value.as_str() == Some("") => {
+                self.problem(&value.mark, format!("\"{}\" can not be empty", key));
+                None
+            }
+            Some((_, value)) => Some(value),
+        }
+    }
+
+    // Check that script exists, relative to the directory of config file.
+    fn script(&mut self, script: &str, mark: &Marker) {
+        match shellexpand::full(script) {
+            Ok(path) => {
+                let dir = self.file.parent().unwrap_or_else(|| Path::new(""));
+                if !dir.join(path.as_ref()).exists() {
+                    self.problem(mark, format!("script file does not exists: {}", path));
+                }
+            }
+            Err(err) => self.problem(mark, format!("Unable to expand path: {}", err)),
+        }
+    }
+
+    // Check that source of link exists, relative to the directory of config file.
+    fn source(&mut self, src: &str, mark: &Marker) {
+        match shellexpand::full(src) {
+            Ok(path) => {
+                let dir = self.file.parent().unwrap_or_else(|| Path::new(""));
+                if !dir.join(path.as_ref()).exists() {
+                    self.warning(
+                        mark,
+                        format!("source file of link does not exists: {}", path),
+                    );
+                }
+            }
+            Err(err) => self.problem(mark, format!("Unable to expand path: {}", err)),
+        }
+    }
+}
+
+// Value of key in the pairs of a mapping.
+fn field<'n>(pairs: &'n [(Node, Node)], key: &str) -> Option<&'n Node> {
+    pairs
+        .iter()
+        .find(|(k, _)| k.as_str() == Some(key))
+        .map(|(_, value)| value)
+}
+
+// Ids of the entries of group, in the sequence form or in its `values`.
+fn group_ids(node: &Node) -> Vec<String> {
+    let entries = match node.kind {
+        Kind::Sequence(ref entries) => entries,
+        Kind::Mapping(ref pairs) => match field(pairs, "values").map(|values| &values.kind) {
+            Some(Kind::Sequence(ref entries)) => entries,
+            _ => return vec![],
+        },
+        _ => return vec![],
+    };
+    entries
+        .iter()
+        .filter_map(|entry| match entry.kind {
+            Kind::Mapping(ref pairs) => match field(pairs, "name").and_then(Node::as_str) {
+                Some(name) => Some(name.to_string()),
+                None => entry_id(pairs).map(|(_, id)| id),
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+// Id of an entry without name, derived in the same way of `Values::id`:
+// the package names, the link destination or the shell command line.
+// Returns the key it was derived from too.
+fn entry_id(pairs: &[(Node, Node)]) -> Option<(&Node, String)> {
+    let scalar = |node: &Node, key: &str| -> Option<String> {
+        match node.kind {
+            Kind::Scalar(ref value, false) => Some(value.clone()),
+            Kind::Mapping(ref pairs) => field(pairs, key)?.as_str().map(String::from),
+            _ => None,
+        }
+    };
+
+    let key = |name: &str| pairs.iter().find(|(k, _)| k.as_str() == Some(name));
+
+    if let Some((key, packages)) = key("package") {
+        let packages = match packages.kind {
+            Kind::Sequence(ref packages) => packages.iter().collect(),
+            _ => vec![packages],
+        };
+        let names: Option<Vec<String>> = packages
+            .into_iter()
+            .map(|package| scalar(package, "name").map(|name| name.trim().to_string()))
+            .collect();
+        return names.map(|names| (key, names.join(" ")));
+    }
+    if let Some((key, link)) = key("link") {
+        let dst = match link.kind {
+            Kind::Scalar(ref value, false) => SymLink::from_str(value).ok().map(|link| link.dst),
+            _ => scalar(link, "dst"),
+        };
+        return dst.map(|dst| (key, dst));
+    }
+    if let Some((key, shell)) = key("shell") {
+        if let Kind::Mapping(ref shell_pairs) = shell.kind {
+            if let Some(script) = field(shell_pairs, "script").and_then(Node::as_str) {
+                let mut line = vec![script.to_string()];
+                if let Some(args) = field(shell_pairs, "args") {
+                    match args.kind {
+                        Kind::Sequence(ref args) => {
+                            line.extend(args.iter().filter_map(Node::as_str).map(String::from))
+                        }
+                        _ => line.extend(args.as_str().map(String::from)),
+                    }
+                }
+                return Some((key, line.join(" ")));
+            }
+        }
+        return scalar(shell, "command").map(|command| (key, command));
+    }
+    None
+}
+
+// Find the most similar known key to suggest as a fix for a typo.
+fn suggest<'k>(key: &str, known: &[&'k str]) -> Option<&'k str> {
+    known
+        .iter()
+        .map(|candidate| (strsim::damerau_levenshtein(key, candidate), *candidate))
+        .filter(|(distance, _)| *distance <= 2)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    fn messages(content: &str) -> Vec<String> {
+        validate(content, Path::new("dotfiles.yml"))
+            .iter()
+            .map(|problem| problem.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_config() {
+        let src = NamedTempFile::new().unwrap();
+        let content = format!(
+            "
+dev:
+  - package: [tmux, fzf]
+  - package:
+      name: neovim
+    link:
+      src: {:?}
+      dst: ~/.config/nvim/init.vim
+      relink: true
+    shell: nvim +PlugInstall +qall
+",
+            src.path()
+        );
+        assert!(messages(&content).is_empty());
+    }
+
+    #[test]
+    fn test_unknown_keys() {
+        let content = "
+dev:
+  - link:
+      src: /
+      dst: /tmp/foo
+      rellink: true
+  - shell:
+      comand: ls
+    pakage: tmux
+";
+        assert_eq!(
+            vec![
+                "dotfiles.yml:6:7: unknown key \"rellink\", did you mean \"relink\"?",
+                "dotfiles.yml:8:7: unknown key \"comand\", did you mean \"command\"?",
+                "dotfiles.yml:8:7: missing required key \"command\"",
+                "dotfiles.yml:9:5: unknown key \"pakage\", did you mean \"package\"?",
+            ],
+            messages(content)
+        );
+    }
+
+    #[test]
+    fn test_empty_required_fields() {
+        let content = "
+dev:
+  - package:
+      name: ''
+  - shell:
+      command:
+";
+        assert_eq!(
+            vec![
+                "dotfiles.yml:4:13: \"name\" can not be empty",
+                "dotfiles.yml:6:7: \"command\" can not be empty",
+            ],
+            messages(content)
+        );
+    }
+
+    #[test]
+    fn test_nonexistent_src() {
+        let content = "
+dev:
+  - link: /tmp/ipa-src-invalid -> /tmp/ipa-dst-invalid
+";
+        assert_eq!(
+            vec!["dotfiles.yml:3:11: source file of link does not exists: /tmp/ipa-src-invalid"],
+            messages(content)
+        );
+    }
+
+    #[test]
+    fn test_relative_src() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("vimrc"), "").unwrap();
+        let content = "
+dev:
+  - link: vimrc -> ~/.vimrc
+  - link: missing -> ~/.missing
+";
+        let problems: Vec<String> = validate(content, &dir.path().join("dotfiles.yml"))
+            .iter()
+            .map(|problem| problem.message.clone())
+            .collect();
+        assert_eq!(
+            vec!["source file of link does not exists: missing"],
+            problems
+        );
+    }
+
+    #[test]
+    fn test_known_keys() {
+        assert!(fields::<Shell>().contains(&"become"));
+        assert!(!fields::<Shell>().contains(&"become_user"));
+        assert!(fields::<Values>().contains(&"package"));
+        assert_eq!(&["name"], fields::<Package>());
+        assert!(fields::<GroupOptions>().contains(&"values"));
+    }
+
+    #[test]
+    fn test_duplicated_ids() {
+        let content = "
+dev:
+  - package: git
+  - package:
+      name: git
+  - name: git
+    package: git
+  - link: / -> ~/foo
+  - link:
+      src: /tmp
+      dst: ~/foo
+  - shell: make
+  - shell:
+      command: make
+  - shell:
+      script: src/lib.rs
+      args: [a]
+  - shell: src/lib.rs a
+  - package: [git, tmux]
+";
+        assert_eq!(
+            vec![
+                "dotfiles.yml:4:5: duplicated id git, add a name to tell them apart",
+                "dotfiles.yml:6:11: duplicated name git",
+                "dotfiles.yml:9:5: duplicated id ~/foo, add a name to tell them apart",
+                "dotfiles.yml:13:5: duplicated id make, add a name to tell them apart",
+                "dotfiles.yml:18:5: duplicated id src/lib.rs a, add a name to tell them apart",
+            ],
+            messages(content)
+        );
+    }
+
+    #[test]
+    fn test_shell_script() {
+        let content = "
+dev:
+  - shell:
+      script: src/lib.rs
+  - shell:
+      script: scripts/missing.sh
+      args: [foo]
+  - shell:
+      command: ls
+      script: src/main.rs
+";
+        assert_eq!(
+            vec![
+                "dotfiles.yml:6:15: script file does not exists: scripts/missing.sh",
+                "dotfiles.yml:10:7: only one of \"command\" or \"script\" can be used",
+            ],
+            messages(content)
+        );
+    }
+
+    #[test]
+    fn test_group_options() {
+        let content = "
+dev:
+  after: [base, gui]
+  ordr: 1
+  values:
+    - package: neovim
+      requires: [tools]
+base:
+  - name: git
+    package: git
+  - name: git
+    shell: git --version
+    requires: [base/git, gui/i3, base/gti]
+  - package: [fzf, ripgrep]
+    requires: [dev/neovim, base/fzf, base/fzf ripgrep]
+";
+        assert_eq!(
+            vec![
+                "dotfiles.yml:3:17: unknown group gui",
+                "dotfiles.yml:4:3: unknown key \"ordr\", did you mean \"order\"?",
+                "dotfiles.yml:7:18: unknown group tools",
+                "dotfiles.yml:11:11: duplicated name git",
+                "dotfiles.yml:13:26: unknown group gui",
+                "dotfiles.yml:13:34: unknown value base/gti",
+                "dotfiles.yml:15:28: unknown value base/fzf",
+            ],
+            messages(content)
+        );
+    }
+
+    #[test]
+    fn test_hooks() {
+        let content = "
+hooks:
+  after: notify-send done
+dev:
+  hooks:
+    before: ['']
+    after:
+      - comand: fc-cache -f
+    only_change: true
+  values:
+    - package: neovim
+";
+        assert_eq!(
+            vec![
+                "dotfiles.yml:6:14: shell command can not be empty",
+                "dotfiles.yml:8:9: unknown key \"comand\", did you mean \"command\"?",
+                "dotfiles.yml:8:9: missing required key \"command\"",
+                "dotfiles.yml:9:5: unknown key \"only_change\", did you mean \"only_changed\"?",
+            ],
+            messages(content)
+        );
+    }
+
+    #[test]
+    fn test_handlers() {
+        let content = "
+dev:
+  - link: / -> /tmp/foo
+    notify: [restart dunst, reload i3]
+handlers:
+  restart dunst: systemctl --user restart dunst
+  reload: ''
+";
+        assert_eq!(
+            vec![
+                "dotfiles.yml:4:29: unknown handler reload i3",
+                "dotfiles.yml:7:11: shell command can not be empty",
+            ],
+            messages(content)
+        );
+    }
+
+    #[test]
+    fn test_tags() {
+        let content = "
+dev:
+  tags: [rust, {}]
+  values:
+    - package: rustup
+      tags: ''
+    - package: go
+      tags: go
+";
+        assert_eq!(
+            vec![
+                "dotfiles.yml:3:16: tag must be a non empty string",
+                "dotfiles.yml:6:13: tag must be a non empty string",
+            ],
+            messages(content)
+        );
+    }
+
+    #[test]
+    fn test_invalid_yaml() {
+        let problems = messages("dev:\n  - package: [tmux\n");
+        assert_eq!(1, problems.len());
+        assert!(
+            problems[0].starts_with("dotfiles.yml:3:1: "),
+            "{}",
+            problems[0]
+        );
+    }
+}
@@ -0,0 +1,56 @@
+use ipa::config::Config;
+use ipa::pacman::{Package, PackageManagement, Pacman};
+use ipa::status;
+use std::env;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+
+// Put a stub of pacman first on PATH, recording its arguments and reporting
+// git as explicitly installed and openssl as installed as a dependency.
+// PATH is shared by the whole process, so this file has a single test.
+#[test]
+fn test_pacman_query() {
+    let dir = tempfile::tempdir().unwrap();
+    let stub = dir.path().join("pacman");
+    fs::write(
+        &stub,
+        "#!/bin/sh
+echo \"$@\" >> \"$(dirname \"$0\")/args\"
+case \"$*\" in
+    \"-Q git\" | \"-Q openssl\") exit 0 ;;
+    -Qqe) echo git ;;
+    *) exit 1 ;;
+esac
+",
+    )
+    .unwrap();
+    fs::set_permissions(&stub, fs::Permissions::from_mode(0o755)).unwrap();
+    let path = env::var("PATH").unwrap_or_default();
+    env::set_var("PATH", format!("{}:{}", dir.path().display(), path));
+
+    let pacman = Pacman::new();
+    assert!(pacman.installed(&Package::new("git")).unwrap());
+    assert!(pacman.installed(&Package::new("openssl")).unwrap());
+    assert!(!pacman.installed(&Package::new("htop")).unwrap());
+    assert_eq!(vec![Package::new("git")], pacman.list_installed().unwrap());
+
+    assert_eq!(
+        "-Q git\n-Q openssl\n-Q htop\n-Qqe\n",
+        fs::read_to_string(dir.path().join("args")).unwrap()
+    );
+
+    // Packages installed as dependencies are not missing for status.
+    let config = Config::new("base:\n  - package: [git, openssl, htop]\n").unwrap();
+    let statuses: Vec<(bool, String)> = status::check(&config, &pacman)
+        .into_iter()
+        .map(|check| (check.in_sync, check.status))
+        .collect();
+    assert_eq!(
+        vec![
+            (true, String::from("installed")),
+            (true, String::from("installed")),
+            (false, String::from("missing")),
+        ],
+        statuses
+    );
+}
@@ -17,7 +17,7 @@ fn test_setup_pacman() {
     )
     .unwrap();
     let pacman = pacman::Pacman::new();
-    let ipa = runner::Ipa::new(config, &pacman);
+    let ipa = runner::Ipa::new(config, &pacman).without_state();
 
     assert!(ipa.setup().is_ok());
     assert!(pacman.is_installed("fzf").unwrap());